@@ -1,4 +1,4 @@
-use judger::{Config, SeccompRuleName, run};
+use judger::{ByteSize, Config, SeccompRuleName, run};
 use std::io::Write;
 
 fn main() {
@@ -34,7 +34,7 @@ func main() {
     let config = Config {
         exe_path: "gogo".to_string(),
         // go 更高的内存需求
-        max_memory: 512 * 1024 * 1024,
+        max_memory: Some(ByteSize::mib(512)),
         input_path: input_file_path.to_string(),
         output_path: "1.out".to_string(),
         error_path: "1.err".to_string(),
@@ -1,4 +1,4 @@
-use judger::{Config, SeccompRuleName, run};
+use judger::{ByteSize, Config, LanguageRegistry, run};
 use std::io::Write;
 
 fn main() {
@@ -31,26 +31,22 @@ public class Main {
         .write_all(input_data.as_bytes())
         .expect("Unable to write input data");
 
-    let config = Config {
-        exe_path: "/usr/bin/java".to_string(),
-        args: vec![
-            "/usr/bin/java".to_string(),
-            "-Xmx256M".to_string(),
-            "-XX:CompressedClassSpaceSize=64M".to_string(),
-            tmp_file_path.to_string(),
-        ],
-        // 对于java 真实的内存限制是INFINITY，因为java本身会有额外的内存开销
-        // 这里的内存限制主要是为了在程序运行后得到MLE状态
-        max_memory: 128 * 1024 * 1024,
-        max_cpu_time: 2000,
-        max_real_time: 4000,
-        input_path: input_file_path.to_string(),
-        output_path: "1.out".to_string(),
-        error_path: "1.err".to_string(),
-        log_path: "judger.log".to_string(),
-        seccomp_rule_name: Some(SeccompRuleName::Java),
-        ..Default::default()
-    };
+    let languages = LanguageRegistry::default();
+    let java = languages.get("java").expect("No java profile");
+    let config = java.run_config(
+        tmp_file_path,
+        "",
+        Config {
+            // 对于java 真实的内存限制是INFINITY，因为java本身会有额外的内存开销
+            // 这里的内存限制主要是为了在程序运行后得到MLE状态
+            max_memory: Some(ByteSize::mib(128)),
+            input_path: input_file_path.to_string(),
+            output_path: "1.out".to_string(),
+            error_path: "1.err".to_string(),
+            log_path: "judger.log".to_string(),
+            ..Default::default()
+        },
+    );
 
     let result = run(&config, None);
 
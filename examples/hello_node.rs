@@ -1,4 +1,4 @@
-use judger::{Config, SeccompRuleName, run};
+use judger::{ByteSize, Config, LanguageRegistry, run};
 use std::io::Write;
 
 fn main() {
@@ -20,20 +20,21 @@ console.log('Hello, ' + require('fs').readFileSync(0, 'utf-8').trim() + '!');
         .expect("Unable to write input data");
     drop(input_file);
 
-    let config = Config {
-        exe_path: "/usr/bin/node".to_string(),
-        args: vec!["/usr/bin/node".to_string(), tmp_file_path.to_string()],
-        max_cpu_time: 2000,
-        max_real_time: 4000,
-        // nodejs需要更多内存
-        max_memory: 256 * 1024 * 1024,
-        input_path: input_file_path.to_string(),
-        output_path: "1.out".to_string(),
-        error_path: "1.err".to_string(),
-        log_path: "judger.log".to_string(),
-        seccomp_rule_name: Some(SeccompRuleName::Node),
-        ..Default::default()
-    };
+    let languages = LanguageRegistry::default();
+    let node = languages.get("node").expect("No node profile");
+    let config = node.run_config(
+        tmp_file_path,
+        "",
+        Config {
+            // nodejs需要更多内存
+            max_memory: Some(ByteSize::mib(256)),
+            input_path: input_file_path.to_string(),
+            output_path: "1.out".to_string(),
+            error_path: "1.err".to_string(),
+            log_path: "judger.log".to_string(),
+            ..Default::default()
+        },
+    );
 
     let result = run(&config, None);
 
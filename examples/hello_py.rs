@@ -1,4 +1,4 @@
-use judger::{Config, SeccompRuleName, run};
+use judger::{Config, LanguageRegistry, run};
 use std::io::Write;
 
 fn main() {
@@ -19,18 +19,20 @@ print(f"Hello, {name}!")
         .write_all(input_data.as_bytes())
         .expect("Unable to write input data");
 
-    let config = Config {
-        exe_path: "/usr/bin/python3".to_string(),
-        args: vec!["/usr/bin/python3".to_string(), tmp_file_path.to_string()],
-        max_cpu_time: 2000,
-        max_real_time: 4000,
-        input_path: input_file_path.to_string(),
-        output_path: "1.out".to_string(),
-        error_path: "1.err".to_string(),
-        log_path: "judger.log".to_string(),
-        seccomp_rule_name: Some(SeccompRuleName::Python),
-        ..Default::default()
-    };
+    let languages = LanguageRegistry::default();
+    let python = languages.get("python").expect("No python profile");
+    // The profile doubles the time limits for python.
+    let config = python.run_config(
+        tmp_file_path,
+        "",
+        Config {
+            input_path: input_file_path.to_string(),
+            output_path: "1.out".to_string(),
+            error_path: "1.err".to_string(),
+            log_path: "judger.log".to_string(),
+            ..Default::default()
+        },
+    );
 
     let result = run(&config, None);
 
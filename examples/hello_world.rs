@@ -1,4 +1,4 @@
-use judger::{Config, SeccompRuleName, run};
+use judger::{Config, SeccompRuleName, compile, run};
 use std::io::Write;
 
 fn main() {
@@ -23,9 +23,22 @@ int main() {
         .write_all(input_data.as_bytes())
         .expect("Unable to write input data");
 
-    let _ = std::process::Command::new("gcc")
-        .args([tmp_file_path, "-o", "hello_world"])
-        .output();
+    let compile_config = Config {
+        exe_path: "/usr/bin/gcc".to_string(),
+        args: vec![
+            "/usr/bin/gcc".to_string(),
+            tmp_file_path.to_string(),
+            "-o".to_string(),
+            "hello_world".to_string(),
+        ],
+        input_path: "/dev/null".to_string(),
+        output_path: "compile.out".to_string(),
+        error_path: "compile.err".to_string(),
+        log_path: "judger.log".to_string(),
+        ..Config::compiler()
+    };
+    let compile_result = compile(&compile_config);
+    println!("{:?}", compile_result);
 
     let config = Config {
         exe_path: "hello_world".to_string(),
@@ -45,6 +58,8 @@ int main() {
     let _ = std::fs::remove_file(tmp_file_path);
     let _ = std::fs::remove_file(input_file_path);
     let _ = std::fs::remove_file("hello_world");
+    let _ = std::fs::remove_file("compile.out");
+    let _ = std::fs::remove_file("compile.err");
     let _ = std::fs::remove_file("1.out");
     let _ = std::fs::remove_file("1.err");
     let _ = std::fs::remove_file("judger.log");
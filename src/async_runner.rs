@@ -0,0 +1,343 @@
+use crate::runner::{self, Exit, Reaped, Sandbox};
+use crate::stdio::{self, Captured, ParentStdio};
+use crate::{ByteSize, Config, ErrorCode, JudgerError, LogLevel, Logger, RunResult, spj};
+use nix::libc;
+use nix::sys::signal::{SigSet, SigmaskHow, Signal, pthread_sigmask};
+use nix::unistd::Uid;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::time::Duration;
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
+
+/// Shortest time between two checks of the CPU time of a running program.
+const MIN_CPU_CHECK_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Runs the judger with the given configuration on the tokio runtime.
+/// Behaves like [`run`](crate::run), but waits for the program through its pidfd instead of
+/// blocking a thread, so one thread can supervise many runs at once.
+/// Dropping the future kills the program and its interactor or special judge.
+///
+/// Asynchronous runs are not traced, so a blocked syscall kills the program at once, even if
+/// it handles SIGSYS. The run is reported as `SyscallViolation` without [`RunResult::syscall`].
+/// # Arguments
+/// * `config` - A reference to the `Config` struct containing the judger configuration
+/// * `interactor` - An optional `Config` for a testlib interactor, as for [`run`](crate::run)
+/// # Returns
+/// * `Result<RunResult, JudgerError>` - The verdict of the program is in `RunResult::result`.
+///   `Err` means the sandbox itself failed and says at which stage and why.
+pub async fn run_async(
+    config: &Config,
+    interactor: Option<&Config>,
+) -> Result<RunResult, JudgerError> {
+    let mut logger = Logger::new(&config.log_path).map_err(JudgerError::Log)?;
+
+    if !Uid::current().is_root() {
+        logger
+            .write(
+                LogLevel::Fatal,
+                file!(),
+                line!(),
+                format_args!("Error: Root privileges are required to run the judger."),
+            )
+            .map_err(JudgerError::Log)?;
+        return Err(JudgerError::RootRequired);
+    }
+
+    let Some(interactor) = interactor else {
+        let mut result = execute(config, None).await?;
+        if result.result == ErrorCode::Success {
+            if let Some(special_judge) = &config.special_judge {
+                let checker = spj::checker_config(special_judge, config);
+                let checker_result = execute(&checker, None).await?;
+                (result.result, result.score) = spj::checker_verdict(&checker, &checker_result);
+                if result.result == ErrorCode::SpjError {
+                    logger
+                        .write(
+                            LogLevel::Fatal,
+                            file!(),
+                            line!(),
+                            format_args!("Error: Special judge {} failed.", special_judge.exe_path),
+                        )
+                        .map_err(JudgerError::Log)?;
+                }
+            } else if let Some(answer_path) = &config.answer_path {
+                let captured = result.stdout.clone();
+                let output_path = config
+                    .output_file()
+                    .unwrap_or(&config.output_path)
+                    .to_string();
+                let answer_path = answer_path.clone();
+                let mode = config.check_mode;
+                (result.result, result.score) = tokio::task::spawn_blocking(move || {
+                    runner::answer_verdict(captured.as_deref(), &output_path, &answer_path, mode)
+                })
+                .await
+                .map_err(|e| JudgerError::Io {
+                    stage: ErrorCode::SystemError,
+                    source: e.into(),
+                })??;
+            }
+        }
+        return Ok(result);
+    };
+
+    let (inter_config, user_fds, inter_fds) = runner::interaction(config, interactor)?;
+    let (result, inter_result) = tokio::try_join!(
+        execute(config, Some(user_fds)),
+        execute(&inter_config, Some(inter_fds)),
+    )?;
+    runner::interaction_verdict(&mut logger, interactor, result, inter_result)
+}
+
+/// Runs a single program in the sandbox without tracing it and determines its verdict.
+async fn execute(
+    config: &Config,
+    fds: Option<(OwnedFd, OwnedFd)>,
+) -> Result<RunResult, JudgerError> {
+    let mut sandbox = Sandbox::spawn(config, fds, false)?;
+    // On failure, the program is killed as the sandbox is dropped.
+    let mut pump = AsyncPump::new(
+        sandbox.take_stdio(),
+        config.max_output_size,
+        sandbox.pidfd(),
+    )
+    .map_err(|source| JudgerError::Io {
+        stage: ErrorCode::SystemError,
+        source,
+    })?;
+    let exit = {
+        let supervising = supervise(&mut sandbox, config);
+        let pumping = pump.run();
+        tokio::pin!(supervising, pumping);
+        let mut pumped = false;
+        loop {
+            tokio::select! {
+                exit = &mut supervising => break exit,
+                _ = &mut pumping, if !pumped => pumped = true,
+            }
+        }
+    };
+    let exit = exit.map_err(|source| JudgerError::Sys {
+        stage: ErrorCode::WaitFailed,
+        source,
+    })?;
+    sandbox.finish(config, exit, pump.finish())
+}
+
+/// Waits until the program terminates, killing it once it exceeds `max_real_time` or
+/// `max_cpu_time` plus `cpu_time_grace`. The task sleeps until the pidfd becomes readable
+/// or a limit may have been reached.
+async fn supervise(sandbox: &mut Sandbox, config: &Config) -> nix::Result<Exit> {
+    let cpu_time_limit = runner::cpu_time_limit(config);
+    // CPU time is used at most this many times faster than real time.
+    let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+    let mut killed = false;
+    loop {
+        // Job-control stops of the untraced program are collected and ignored.
+        if let Some(Reaped::Exited(exit)) = runner::try_reap(sandbox)? {
+            return Ok(*exit);
+        }
+
+        let mut next_check = None;
+        if !killed {
+            let elapsed = sandbox.elapsed();
+            let real_time_left = config
+                .max_real_time
+                .map(|limit| limit.saturating_sub(elapsed));
+            let cpu_time_left = cpu_time_limit.and_then(|limit| {
+                let usage = sandbox.cpu_usage_usec(config)?;
+                Some(Duration::from_micros(limit.saturating_sub(usage)) / parallelism)
+            });
+            if real_time_left.is_some_and(|left| left.is_zero())
+                || cpu_time_left.is_some_and(|left| left.is_zero())
+            {
+                sandbox.signal(Signal::SIGKILL);
+                killed = true;
+            } else {
+                next_check = match (real_time_left, cpu_time_left) {
+                    (Some(real), Some(cpu)) => Some(real.min(cpu.max(MIN_CPU_CHECK_INTERVAL))),
+                    (Some(real), None) => Some(real),
+                    (None, Some(cpu)) => Some(cpu.max(MIN_CPU_CHECK_INTERVAL)),
+                    (None, None) => None,
+                };
+            }
+        }
+
+        // Registered on every iteration, as the program may exit between the check and the wait.
+        let pidfd = AsyncFd::with_interest(sandbox.pidfd().as_raw_fd(), Interest::READABLE)
+            .map_err(io_errno)?;
+        match next_check {
+            Some(delay) => {
+                tokio::select! {
+                    readable = pidfd.readable() => drop(readable.map_err(io_errno)?),
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            None => drop(pidfd.readable().await.map_err(io_errno)?),
+        }
+    }
+}
+
+/// Feeds the standard input and drains the standard output and error of a running program
+/// on the reactor, like [`Pump`](crate::stdio::Pump) does with threads.
+struct AsyncPump {
+    stdin: Option<(AsyncFd<File>, Vec<u8>)>,
+    stdout: Option<Drain>,
+    stderr: Option<Drain>,
+    limit: Option<ByteSize>,
+    program: OwnedFd,
+}
+
+/// The read end of a captured stream and what was read from it.
+struct Drain {
+    reader: AsyncFd<File>,
+    captured: Captured,
+    closed: bool,
+}
+
+impl AsyncPump {
+    /// Registers the parent ends of the pipes of the program behind `pidfd`.
+    /// The program is killed once it writes more than `limit`. Output beyond it is dropped.
+    fn new(stdio: ParentStdio, limit: Option<ByteSize>, pidfd: &OwnedFd) -> io::Result<AsyncPump> {
+        let register = |fd: OwnedFd| {
+            stdio::set_nonblocking(&fd)?;
+            AsyncFd::new(File::from(fd))
+        };
+        let drain = |fd: Option<OwnedFd>| {
+            fd.map(|fd| {
+                register(fd).map(|reader| Drain {
+                    reader,
+                    captured: Captured::default(),
+                    closed: false,
+                })
+            })
+            .transpose()
+        };
+        Ok(AsyncPump {
+            stdin: stdio
+                .stdin
+                .map(|(writer, bytes)| register(writer).map(|writer| (writer, bytes)))
+                .transpose()?,
+            stdout: drain(stdio.stdout)?,
+            stderr: drain(stdio.stderr)?,
+            limit,
+            program: pidfd.try_clone()?,
+        })
+    }
+
+    /// Pumps until the input is written or closed by the program, and both outputs are closed.
+    async fn run(&mut self) {
+        let AsyncPump {
+            stdin,
+            stdout,
+            stderr,
+            limit,
+            program,
+        } = self;
+        tokio::join!(
+            feed(stdin),
+            drain(stdout, *limit, program),
+            drain(stderr, *limit, program),
+        );
+    }
+
+    /// Collects the output once the program has been reaped. Output still buffered in the
+    /// pipes is read, but descendants that keep writing are not waited for.
+    fn finish(self) -> (Option<Captured>, Option<Captured>) {
+        let AsyncPump {
+            stdout,
+            stderr,
+            limit,
+            program,
+            ..
+        } = self;
+        let collect = |drain: Option<Drain>| {
+            drain.map(|mut drain| {
+                let mut buffer = [0; 8192];
+                while !drain.closed {
+                    match drain.reader.get_ref().read(&mut buffer) {
+                        Ok(0) => drain.closed = true,
+                        Ok(count) => drain.captured.keep(&buffer[..count], limit, &program),
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                        Err(_) => drain.closed = true,
+                    }
+                }
+                drain.captured
+            })
+        };
+        (collect(stdout), collect(stderr))
+    }
+}
+
+/// Writes the bytes of `stdin` to the standard input of the program, then closes it.
+/// Stops early if the program closes its input.
+async fn feed(stdin: &mut Option<(AsyncFd<File>, Vec<u8>)>) {
+    if let Some((writer, bytes)) = stdin {
+        let mut written = 0;
+        while written < bytes.len() {
+            let Ok(mut guard) = writer.writable().await else {
+                break;
+            };
+            match guard.try_io(|writer| write_without_sigpipe(writer.get_ref(), &bytes[written..]))
+            {
+                Ok(Ok(count)) => written += count,
+                Ok(Err(e)) if e.kind() == io::ErrorKind::Interrupted => {}
+                Ok(Err(_)) => break,
+                Err(_would_block) => {}
+            }
+        }
+    }
+    *stdin = None;
+}
+
+/// Reads a standard output or error of the program until it is closed.
+/// Kills the program behind `program` once it exceeds `limit`.
+async fn drain(drain: &mut Option<Drain>, limit: Option<ByteSize>, program: &OwnedFd) {
+    let Some(drain) = drain else {
+        return;
+    };
+    let mut buffer = [0; 8192];
+    while !drain.closed {
+        let Ok(mut guard) = drain.reader.readable().await else {
+            break;
+        };
+        match guard.try_io(|reader| reader.get_ref().read(&mut buffer)) {
+            Ok(Ok(0)) => drain.closed = true,
+            Ok(Ok(count)) => drain.captured.keep(&buffer[..count], limit, program),
+            Ok(Err(e)) if e.kind() == io::ErrorKind::Interrupted => {}
+            Ok(Err(_)) => drain.closed = true,
+            Err(_would_block) => {}
+        }
+    }
+}
+
+/// Writes to a pipe, failing with `EPIPE` instead of raising SIGPIPE in the judger
+/// if the program closed its end. The signal is blocked on the calling thread meanwhile.
+fn write_without_sigpipe(mut file: &File, bytes: &[u8]) -> io::Result<usize> {
+    let mut sigpipe = SigSet::empty();
+    sigpipe.add(Signal::SIGPIPE);
+    let mut mask = SigSet::empty();
+    let _ = pthread_sigmask(SigmaskHow::SIG_BLOCK, Some(&sigpipe), Some(&mut mask));
+    let result = file.write(bytes);
+    if !mask.contains(Signal::SIGPIPE)
+        && result
+            .as_ref()
+            .is_err_and(|e| e.raw_os_error() == Some(libc::EPIPE))
+    {
+        // Discards the SIGPIPE raised by this write before it is unblocked.
+        let timeout = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe { libc::sigtimedwait(sigpipe.as_ref(), std::ptr::null_mut(), &timeout) };
+    }
+    let _ = pthread_sigmask(SigmaskHow::SIG_SETMASK, Some(&mask), None);
+    result
+}
+
+/// Converts an I/O error of the reactor into an errno.
+fn io_errno(error: std::io::Error) -> nix::Error {
+    nix::Error::from_raw(error.raw_os_error().unwrap_or(nix::libc::EIO))
+}
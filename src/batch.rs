@@ -0,0 +1,148 @@
+use crate::runner::run_privileged;
+use crate::{Config, ErrorCode, JudgerError, LogLevel, Logger, OutputSink, RunResult};
+use nix::unistd::Uid;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+/// A single test case of a batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    /// Path to the input file.
+    pub input_path: String,
+    /// Path the program output is written to.
+    pub output_path: String,
+    /// Path to the expected answer, if the output should be checked.
+    pub answer_path: Option<String>,
+}
+
+/// How a batch of test cases is run.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions {
+    /// Stop at the first test case that is not `Success`.
+    pub stop_on_failure: bool,
+    /// Maximum number of test cases run at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            stop_on_failure: false,
+            concurrency: 1,
+        }
+    }
+}
+
+/// Runs the program of `config` against every test case.
+/// Each case runs with a copy of `config` whose input, output and answer paths come from the case,
+/// replacing its `stdin` and `stdout`.
+/// With a `concurrency` above 1, the index of the case is appended to the `error_path` of the
+/// program, of its special judge and of the interactor, e.g. `spj.err.3`, so that cases running
+/// at the same time do not share these files. Files under `/dev` are shared as they are.
+/// Root privileges are checked once for the whole batch.
+/// # Arguments
+/// * `config` - The base configuration shared by all test cases
+/// * `interactor` - An optional `Config` for a testlib interactor, as in [`crate::run`]
+/// * `cases` - The test cases, in order
+/// * `options` - Concurrency and stop-on-failure settings
+/// # Returns
+/// * `Result<Vec<RunResult>, JudgerError>` - One result per test case, in order. With `stop_on_failure`,
+///   the results end at the first failed case. The first sandbox failure fails the whole batch.
+pub fn run_batch(
+    config: &Config,
+    interactor: Option<&Config>,
+    cases: &[TestCase],
+    options: BatchOptions,
+) -> Result<Vec<RunResult>, JudgerError> {
+    if !Uid::current().is_root() {
+        let mut logger = Logger::new(&config.log_path).map_err(JudgerError::Log)?;
+        logger
+            .write(
+                LogLevel::Fatal,
+                file!(),
+                line!(),
+                format_args!("Error: Root privileges are required to run the judger."),
+            )
+            .map_err(JudgerError::Log)?;
+        return Err(JudgerError::RootRequired);
+    }
+
+    let results: Vec<Mutex<Option<Result<RunResult, JudgerError>>>> =
+        cases.iter().map(|_| Mutex::new(None)).collect();
+    // Cases are taken in order, so every case before a failure has been started.
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let concurrency = options.concurrency.clamp(1, cases.len().max(1));
+    thread::scope(|scope| {
+        for _ in 0..concurrency {
+            scope.spawn(|| {
+                while !stop.load(Ordering::SeqCst) {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(case) = cases.get(index) else {
+                        break;
+                    };
+                    let mut case_config = Config {
+                        input_path: case.input_path.clone(),
+                        output_path: case.output_path.clone(),
+                        answer_path: case.answer_path.clone(),
+                        stdin: None,
+                        stdout: None,
+                        ..config.clone()
+                    };
+                    let mut case_interactor = interactor.cloned();
+                    if concurrency > 1 {
+                        separate_error_paths(&mut case_config, index);
+                        if let Some(special_judge) = &mut case_config.special_judge {
+                            separate_error_paths(special_judge, index);
+                        }
+                        if let Some(interactor) = &mut case_interactor {
+                            separate_error_paths(interactor, index);
+                        }
+                    }
+                    let result = run_privileged(&case_config, case_interactor.as_ref());
+                    let failed =
+                        !matches!(&result, Ok(result) if result.result == ErrorCode::Success);
+                    if failed && (options.stop_on_failure || result.is_err()) {
+                        stop.store(true, Ordering::SeqCst);
+                    }
+                    if let Ok(mut slot) = results[index].lock() {
+                        *slot = Some(result);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut batch = Vec::with_capacity(cases.len());
+    for slot in results {
+        let Some(result) = slot.into_inner().ok().flatten() else {
+            break;
+        };
+        let result = result?;
+        let failed = result.result != ErrorCode::Success;
+        batch.push(result);
+        if failed && options.stop_on_failure {
+            break;
+        }
+    }
+    Ok(batch)
+}
+
+/// Appends the index of the case to the files `config` writes its standard error to.
+fn separate_error_paths(config: &mut Config, index: usize) {
+    config.error_path = case_path(&config.error_path, index);
+    if let Some(OutputSink::Path(path)) = &mut config.stderr {
+        *path = case_path(path, index);
+    }
+}
+
+/// `path` with the index of the case appended. Device files are left unchanged.
+fn case_path(path: &str, index: usize) -> String {
+    if path.is_empty() || path.starts_with("/dev/") {
+        path.to_string()
+    } else {
+        format!("{}.{}", path, index)
+    }
+}
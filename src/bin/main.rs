@@ -1,13 +1,86 @@
-use clap::Parser;
-use judger::{Config, SeccompRuleName, run};
+use clap::{Parser, Subcommand, ValueEnum};
+use judger::{
+    BatchOptions, BindMount, ByteSize, CONFIG_SCHEMA, CgroupConfig, CheckMode, Config,
+    LanguageRegistry, MemoryLimitMode, RootFs, SeccompPolicy, SeccompRuleName, TestCase, judge,
+    run, run_batch,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(Parser, Debug)]
 #[command(name = "judger", version = VERSION, about = "A Rust-based code execution judger.")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    run: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the program once (the same as running without a subcommand)
+    Run {
+        #[command(flatten)]
+        run: Box<Args>,
+    },
+    /// Print the JSON Schema of the config accepted by --config
+    Schema,
+    /// Seccomp policy utilities
+    Seccomp {
+        #[command(subcommand)]
+        command: SeccompCommand,
+    },
+    /// Run the program against many test cases
+    Batch {
+        #[arg(
+            long,
+            help = "JSON file with a list of {input_path, output_path, answer_path}"
+        )]
+        cases: String,
+        #[arg(long, help = "Stop at the first failed test case")]
+        stop_on_failure: bool,
+        #[arg(long, help = "Number of test cases run in parallel (default: 1)")]
+        concurrency: Option<usize>,
+        #[command(flatten)]
+        run: Box<Args>,
+    },
+    /// Compile, run and check a submission against a problem package
+    Judge {
+        #[arg(
+            long,
+            help = "Problem package directory (with problem.toml or problem.json)"
+        )]
+        problem: String,
+        #[arg(
+            long,
+            help = "Language of the submission (c, cpp, java, python, go, node, rust)"
+        )]
+        language: String,
+        #[arg(long, help = "Source file of the submission")]
+        source: String,
+        #[arg(long, help = "File with extra language profiles (JSON or TOML)")]
+        languages: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SeccompCommand {
+    /// Validate a seccomp policy file (JSON or TOML)
+    Check { file: String },
+}
+
+#[derive(clap::Args, Debug)]
 pub(crate) struct Args {
     #[arg(long, help = "Max CPU Time (ms)")]
     max_cpu_time: Option<i32>,
+    #[arg(
+        long,
+        help = "CPU time allowed beyond the limit before killing (ms, default 50)"
+    )]
+    cpu_time_grace: Option<i32>,
+    #[arg(long, help = "Count the CPU time of child processes too")]
+    include_descendant_cpu_time: bool,
     #[arg(long, help = "Max Real Time (ms)")]
     max_real_time: Option<i32>,
     #[arg(long, help = "Max Memory (byte)")]
@@ -17,14 +90,25 @@ pub(crate) struct Args {
         help = "Only check memory usage, do not setrlimit (default: false)"
     )]
     memory_limit_check_only: Option<bool>,
+    #[arg(
+        long,
+        conflicts_with = "memory_limit_check_only",
+        help = "How the memory limit is enforced (default: rlimit)"
+    )]
+    memory_limit_mode: Option<MemoryLimitMode>,
     #[arg(long, help = "Max Stack (byte, default 16M)")]
     max_stack: Option<i64>,
     #[arg(long, help = "Max Process Number")]
     max_process_number: Option<i32>,
     #[arg(long, help = "Max Output Size (byte)")]
     max_output_size: Option<i64>,
-    #[arg(long, help = "Exe Path")]
-    exe_path: String,
+    #[arg(
+        long,
+        help = "Config file (JSON or TOML, - for JSON on stdin), overridden by the other flags"
+    )]
+    config: Option<String>,
+    #[arg(long, required_unless_present = "config", help = "Exe Path")]
+    exe_path: Option<String>,
     #[arg(long, help = "Input Path")]
     input_path: Option<String>,
     #[arg(long, help = "Output Path")]
@@ -35,6 +119,11 @@ pub(crate) struct Args {
     log_path: Option<String>,
     #[arg(long, help = "Seccomp Rule Name")]
     seccomp_rule_name: Option<SeccompRuleName>,
+    #[arg(
+        long,
+        help = "Seccomp policy file (JSON or TOML), overrides the rule name"
+    )]
+    seccomp_policy: Option<String>,
     #[arg(long, help = "UID (default: 65534)")]
     uid: Option<u32>,
     #[arg(long, help = "GID (default: 65534)")]
@@ -43,32 +132,382 @@ pub(crate) struct Args {
     args: Vec<String>,
     #[arg(long, help = "Env")]
     env: Vec<String>,
+    #[arg(long, help = "Parent cgroup v2 directory (enables the cgroup backend)")]
+    cgroup: Option<String>,
+    #[arg(long, value_delimiter = ',', help = "Namespaces to unshare")]
+    namespaces: Vec<Namespace>,
+    #[arg(
+        long,
+        help = "Mount point of the sandbox root (enables the read-only root)"
+    )]
+    rootfs: Option<String>,
+    #[arg(
+        long,
+        help = "Extra read-only bind mount into the sandbox root (SRC[:DST])"
+    )]
+    bind: Vec<String>,
+    #[arg(long, help = "Size of the sandbox root tmpfs (byte, default 16M)")]
+    root_size: Option<u64>,
+    #[arg(long, help = "Working directory inside the sandbox root")]
+    work_dir: Option<String>,
+    #[arg(long, help = "Answer Path (check the output against it)")]
+    answer_path: Option<String>,
+    #[arg(long, help = "Check Mode (default: ignore-trailing-whitespace)")]
+    check_mode: Option<CheckModeArg>,
+    #[arg(long, help = "Absolute and relative error for the float check mode")]
+    epsilon: Option<f64>,
+    #[arg(long, help = "Special judge (testlib checker) run on the output")]
+    spj_path: Option<String>,
+    #[arg(long, help = "Special judge message file (default: spj.err)")]
+    spj_error_path: Option<String>,
+    #[arg(
+        long,
+        help = "Interactor (testlib) connected to the program's stdin and stdout"
+    )]
+    interactor_path: Option<String>,
+    #[arg(long, help = "Interactor message file (default: interactor.err)")]
+    interactor_error_path: Option<String>,
+    #[arg(
+        long,
+        help = "UID of the special judge and interactor (default: 65534)"
+    )]
+    judge_uid: Option<u32>,
+    #[arg(
+        long,
+        help = "GID of the special judge and interactor (default: 65534)"
+    )]
+    judge_gid: Option<u32>,
+    #[arg(
+        long,
+        help = "Seccomp Rule Name of the special judge and interactor (default: compiler)"
+    )]
+    judge_seccomp_rule_name: Option<SeccompRuleName>,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum CheckModeArg {
+    Exact,
+    IgnoreTrailingWhitespace,
+    Token,
+    Float,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum Namespace {
+    Mount,
+    Pid,
+    Ipc,
+    Uts,
+    Net,
 }
+
 fn main() {
-    let args = Args::parse();
-
-    let config = Config {
-        max_cpu_time: args.max_cpu_time.unwrap_or(-1),
-        max_real_time: args.max_real_time.unwrap_or(-1),
-        max_memory: args.max_memory.unwrap_or(-1),
-        max_stack: args.max_stack.unwrap_or(16 * 1024 * 1024),
-        max_process_number: args.max_process_number.unwrap_or(-1),
-        max_output_size: args.max_output_size.unwrap_or(-1),
-        exe_path: args.exe_path,
-        input_path: args.input_path.unwrap_or_else(|| "/dev/stdin".to_string()),
-        output_path: args
-            .output_path
-            .unwrap_or_else(|| "/dev/stdout".to_string()),
-        error_path: args.error_path.unwrap_or_else(|| "/dev/stderr".to_string()),
-        args: args.args,
-        env: args.env,
-        log_path: args.log_path.unwrap_or_else(|| "judger.log".to_string()),
-        seccomp_rule_name: args.seccomp_rule_name,
-        uid: args.uid.unwrap_or(65534),
-        gid: args.gid.unwrap_or(65534),
-    };
+    let cli = Cli::parse();
 
-    let result = run(&config, None);
+    match cli.command {
+        Some(Command::Seccomp {
+            command: SeccompCommand::Check { file },
+        }) => check_seccomp_policy(&file),
+        Some(Command::Batch {
+            cases,
+            stop_on_failure,
+            concurrency,
+            run: args,
+        }) => {
+            let cases = read_cases(&cases);
+            let interactor = build_interactor(&args);
+            let config = build_config(*args);
+            let options = BatchOptions {
+                stop_on_failure,
+                concurrency: concurrency.unwrap_or(1),
+            };
+            let result =
+                run_batch(&config, interactor.as_ref(), &cases, options).map_err(|e| e.to_string());
+            println!("{}", serde_json::to_string_pretty(&result).unwrap());
+        }
+        Some(Command::Judge {
+            problem,
+            language,
+            source,
+            languages,
+        }) => {
+            let mut registry = LanguageRegistry::default();
+            if let Some(file) = languages
+                && let Err(e) = registry.load_file(&file)
+            {
+                eprintln!("{}: {}", file, e);
+                std::process::exit(1);
+            }
+            let report = judge(&problem, &registry, &language, &source).map_err(|e| e.to_string());
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        }
+        Some(Command::Run { run: args }) => run_once(*args),
+        Some(Command::Schema) => print!("{}", CONFIG_SCHEMA),
+        None => run_once(cli.run),
+    }
+}
 
+fn run_once(args: Args) {
+    let interactor = build_interactor(&args);
+    let config = build_config(args);
+    let result = run(&config, interactor.as_ref()).map_err(|e| e.to_string());
     println!("{}", serde_json::to_string_pretty(&result).unwrap());
 }
+
+fn read_cases(file: &str) -> Vec<TestCase> {
+    std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            eprintln!("{}: {}", file, e);
+            std::process::exit(1);
+        })
+}
+
+fn check_seccomp_policy(file: &str) {
+    match SeccompPolicy::from_file(file).and_then(|policy| policy.validate()) {
+        Ok(()) => println!("{}: OK", file),
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Config of a judge program (special judge or interactor) with the limits of [`Config::compiler`],
+/// run as `nobody` unless `--judge-uid` and `--judge-gid` say otherwise.
+fn judge_program(exe_path: String, error_path: String, args: &Args) -> Config {
+    let compiler = Config::compiler();
+    Config {
+        exe_path,
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path,
+        log_path: args
+            .log_path
+            .clone()
+            .unwrap_or_else(|| "judger.log".to_string()),
+        seccomp_rule_name: args
+            .judge_seccomp_rule_name
+            .clone()
+            .or(compiler.seccomp_rule_name.clone()),
+        uid: args.judge_uid.unwrap_or(compiler.uid),
+        gid: args.judge_gid.unwrap_or(compiler.gid),
+        ..compiler
+    }
+}
+
+fn build_interactor(args: &Args) -> Option<Config> {
+    args.interactor_path.clone().map(|exe_path| {
+        judge_program(
+            exe_path,
+            args.interactor_error_path
+                .clone()
+                .unwrap_or_else(|| "interactor.err".to_string()),
+            args,
+        )
+    })
+}
+
+/// Defaults of the command line, under the fields of a config file.
+fn default_config() -> Config {
+    Config {
+        max_cpu_time: None,
+        max_real_time: None,
+        max_memory: None,
+        max_stack: Some(ByteSize::mib(16)),
+        max_process_number: None,
+        max_output_size: None,
+        input_path: "/dev/stdin".to_string(),
+        output_path: "/dev/stdout".to_string(),
+        error_path: "/dev/stderr".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: None,
+        uid: 65534,
+        gid: 65534,
+        ..Default::default()
+    }
+}
+
+/// Reads the config given by `--config`, from stdin (as JSON) if the path is `-`.
+/// Fields missing from the file keep the command line defaults.
+fn read_config(file: &str) -> Config {
+    let content = if file == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(file)
+    };
+    let fields = content
+        .map_err(|e| format!("Failed to read config: {}", e))
+        .and_then(|content| {
+            if file.ends_with(".toml") {
+                toml::from_str::<serde_json::Value>(&content).map_err(|e| e.to_string())
+            } else {
+                serde_json::from_str::<serde_json::Value>(&content).map_err(|e| e.to_string())
+            }
+            .map_err(|e| format!("Invalid config: {}", e))
+        });
+    let config = fields.and_then(|fields| {
+        let serde_json::Value::Object(fields) = fields else {
+            return Err("Invalid config: expected a table of fields".to_string());
+        };
+        let mut config =
+            serde_json::to_value(default_config()).map_err(|e| format!("Invalid config: {}", e))?;
+        if let serde_json::Value::Object(defaults) = &mut config {
+            defaults.extend(fields);
+        }
+        serde_json::from_value(config).map_err(|e| format!("Invalid config: {}", e))
+    });
+    config.unwrap_or_else(|e| {
+        eprintln!("{}: {}", file, e);
+        std::process::exit(1);
+    })
+}
+
+/// Builds the config from `--config`, or the command line defaults, with the given flags applied on top.
+fn build_config(args: Args) -> Config {
+    let mut config = match &args.config {
+        Some(file) => read_config(file),
+        None => default_config(),
+    };
+
+    for namespace in &args.namespaces {
+        match namespace {
+            Namespace::Mount => config.namespaces.mount = true,
+            Namespace::Pid => config.namespaces.pid = true,
+            Namespace::Ipc => config.namespaces.ipc = true,
+            Namespace::Uts => config.namespaces.uts = true,
+            Namespace::Net => config.namespaces.net = true,
+        }
+    }
+
+    if let Some(file) = &args.seccomp_policy {
+        config.seccomp_policy = Some(SeccompPolicy::from_file(file).unwrap_or_else(|e| {
+            eprintln!("{}: {}", file, e);
+            std::process::exit(1);
+        }));
+    }
+
+    let epsilon = args.epsilon.unwrap_or(1e-6);
+    match args.check_mode {
+        Some(CheckModeArg::Exact) => config.check_mode = CheckMode::Exact,
+        Some(CheckModeArg::IgnoreTrailingWhitespace) => {
+            config.check_mode = CheckMode::IgnoreTrailingWhitespace
+        }
+        Some(CheckModeArg::Token) => config.check_mode = CheckMode::Token,
+        Some(CheckModeArg::Float) => {
+            config.check_mode = CheckMode::Float {
+                absolute: epsilon,
+                relative: epsilon,
+            }
+        }
+        None => {}
+    }
+
+    if let Some(exe_path) = args.spj_path.clone() {
+        config.special_judge = Some(Box::new(judge_program(
+            exe_path,
+            args.spj_error_path
+                .clone()
+                .unwrap_or_else(|| "spj.err".to_string()),
+            &args,
+        )));
+    }
+
+    // Limits are given on the command line in the legacy layout, with -1 for unlimited.
+    let mut limits = config.legacy_limits();
+    if let Some(max_cpu_time) = args.max_cpu_time {
+        limits.max_cpu_time = max_cpu_time;
+    }
+    if let Some(cpu_time_grace) = args.cpu_time_grace {
+        limits.cpu_time_grace = cpu_time_grace;
+    }
+    if args.include_descendant_cpu_time {
+        config.include_descendant_cpu_time = true;
+    }
+    if let Some(max_real_time) = args.max_real_time {
+        limits.max_real_time = max_real_time;
+    }
+    if let Some(max_memory) = args.max_memory {
+        limits.max_memory = max_memory;
+    }
+    if let Some(memory_limit_mode) = args.memory_limit_mode {
+        config.memory_limit_mode = memory_limit_mode;
+    } else if let Some(check_only) = args.memory_limit_check_only {
+        config.memory_limit_mode = if check_only {
+            MemoryLimitMode::CheckOnly
+        } else {
+            MemoryLimitMode::Rlimit
+        };
+    }
+    if let Some(max_stack) = args.max_stack {
+        limits.max_stack = max_stack;
+    }
+    if let Some(max_process_number) = args.max_process_number {
+        limits.max_process_number = max_process_number;
+    }
+    if let Some(max_output_size) = args.max_output_size {
+        limits.max_output_size = max_output_size;
+    }
+    if let Err(errors) = config.set_legacy_limits(limits) {
+        for error in errors {
+            eprintln!("--{}: {}", error.field.replace('_', "-"), error.message);
+        }
+        std::process::exit(1);
+    }
+    if let Some(exe_path) = args.exe_path {
+        config.exe_path = exe_path;
+    }
+    if let Some(input_path) = args.input_path {
+        config.input_path = input_path;
+    }
+    if let Some(output_path) = args.output_path {
+        config.output_path = output_path;
+    }
+    if let Some(error_path) = args.error_path {
+        config.error_path = error_path;
+    }
+    if !args.args.is_empty() {
+        config.args = args.args;
+    }
+    if !args.env.is_empty() {
+        config.env = args.env;
+    }
+    if let Some(log_path) = args.log_path {
+        config.log_path = log_path;
+    }
+    if let Some(seccomp_rule_name) = args.seccomp_rule_name {
+        config.seccomp_rule_name = Some(seccomp_rule_name);
+    }
+    if let Some(uid) = args.uid {
+        config.uid = uid;
+    }
+    if let Some(gid) = args.gid {
+        config.gid = gid;
+    }
+    if let Some(parent) = args.cgroup {
+        config
+            .cgroup
+            .get_or_insert_with(CgroupConfig::default)
+            .parent = parent;
+    }
+    if let Some(path) = args.rootfs {
+        config.rootfs.get_or_insert_with(RootFs::default).path = path;
+    }
+    if let Some(rootfs) = &mut config.rootfs {
+        rootfs.bind_mounts.extend(args.bind.iter().map(|bind| {
+            let (source, target) = bind.split_once(':').unwrap_or((bind, bind));
+            BindMount::read_only(source, target)
+        }));
+        if let Some(root_size) = args.root_size {
+            rootfs.root_size = ByteSize(root_size);
+        }
+        if let Some(work_dir) = args.work_dir {
+            rootfs.work_dir = work_dir;
+        }
+    }
+    if let Some(answer_path) = args.answer_path {
+        config.answer_path = Some(answer_path);
+    }
+    config
+}
@@ -0,0 +1,229 @@
+use crate::{
+    ByteSize, CgroupConfig, CheckMode, Config, ConfigError, InputSource, MemoryLimitMode, NOBODY,
+    Namespaces, OutputSink, RootFs, SeccompPolicy, SeccompRuleName,
+};
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+/// Builds a [`Config`] with typed limits.
+/// Standard I/O defaults to `/dev/null`, `argv[0]` to the executable path, the user and group
+/// to [`NOBODY`], and the limits to those of [`Config::default`].
+/// Times and sizes given as `None` are unlimited.
+/// # Example
+/// ```rust
+/// use judger::{ByteSize, Config};
+/// use std::time::Duration;
+/// let result = Config::builder("/bin/true")
+///     .max_cpu_time(Duration::from_secs(1))
+///     .max_real_time(None)
+///     .max_memory(ByteSize::mib(256))
+///     .build();
+/// assert!(result.is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Starts a config for running `exe_path`.
+    pub fn new(exe_path: impl Into<String>) -> Self {
+        let exe_path = exe_path.into();
+        ConfigBuilder {
+            config: Config {
+                args: vec![exe_path.clone()],
+                exe_path,
+                input_path: "/dev/null".to_string(),
+                output_path: "/dev/null".to_string(),
+                error_path: "/dev/null".to_string(),
+                log_path: "/dev/null".to_string(),
+                uid: NOBODY,
+                gid: NOBODY,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets the CPU time limit.
+    pub fn max_cpu_time(mut self, limit: impl Into<Option<Duration>>) -> Self {
+        self.config.max_cpu_time = limit.into();
+        self
+    }
+
+    /// Sets the CPU time the program may use beyond the limit before it is killed.
+    pub fn cpu_time_grace(mut self, grace: Duration) -> Self {
+        self.config.cpu_time_grace = grace;
+        self
+    }
+
+    /// Counts the CPU time of the descendants of the program as well.
+    pub fn include_descendant_cpu_time(mut self, include: bool) -> Self {
+        self.config.include_descendant_cpu_time = include;
+        self
+    }
+
+    /// Sets the real time limit.
+    pub fn max_real_time(mut self, limit: impl Into<Option<Duration>>) -> Self {
+        self.config.max_real_time = limit.into();
+        self
+    }
+
+    /// Sets the memory limit.
+    pub fn max_memory(mut self, limit: impl Into<Option<ByteSize>>) -> Self {
+        self.config.max_memory = limit.into();
+        self
+    }
+
+    /// Sets how the memory limit is enforced.
+    pub fn memory_limit_mode(mut self, mode: MemoryLimitMode) -> Self {
+        self.config.memory_limit_mode = mode;
+        self
+    }
+
+    /// Sets the stack size limit.
+    pub fn max_stack(mut self, limit: impl Into<Option<ByteSize>>) -> Self {
+        self.config.max_stack = limit.into();
+        self
+    }
+
+    /// Sets the maximum number of processes.
+    pub fn max_process_number(mut self, limit: impl Into<Option<NonZeroU32>>) -> Self {
+        self.config.max_process_number = limit.into();
+        self
+    }
+
+    /// Sets the output size limit.
+    pub fn max_output_size(mut self, limit: impl Into<Option<ByteSize>>) -> Self {
+        self.config.max_output_size = limit.into();
+        self
+    }
+
+    /// Appends arguments after `argv[0]`.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Replaces `argv[0]`, which defaults to the executable path.
+    pub fn argv0(mut self, argv0: impl Into<String>) -> Self {
+        match self.config.args.first_mut() {
+            Some(first) => *first = argv0.into(),
+            None => self.config.args.push(argv0.into()),
+        }
+        self
+    }
+
+    /// Appends environment variables, given as `NAME=value`.
+    pub fn env<I, S>(mut self, env: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.env.extend(env.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the file standard input is read from.
+    pub fn input_path(mut self, path: impl Into<String>) -> Self {
+        self.config.input_path = path.into();
+        self
+    }
+
+    /// Sets the file standard output is written to.
+    pub fn output_path(mut self, path: impl Into<String>) -> Self {
+        self.config.output_path = path.into();
+        self
+    }
+
+    /// Sets the file standard error is written to.
+    pub fn error_path(mut self, path: impl Into<String>) -> Self {
+        self.config.error_path = path.into();
+        self
+    }
+
+    /// Sets the source of standard input, instead of `input_path`.
+    pub fn stdin(mut self, source: InputSource) -> Self {
+        self.config.stdin = Some(source);
+        self
+    }
+
+    /// Sets the destination of standard output, instead of `output_path`.
+    pub fn stdout(mut self, sink: OutputSink) -> Self {
+        self.config.stdout = Some(sink);
+        self
+    }
+
+    /// Sets the destination of standard error, instead of `error_path`.
+    pub fn stderr(mut self, sink: OutputSink) -> Self {
+        self.config.stderr = Some(sink);
+        self
+    }
+
+    /// Sets the log file.
+    pub fn log_path(mut self, path: impl Into<String>) -> Self {
+        self.config.log_path = path.into();
+        self
+    }
+
+    /// Sets the seccomp rule, or none at all.
+    pub fn seccomp_rule_name(mut self, rule_name: impl Into<Option<SeccompRuleName>>) -> Self {
+        self.config.seccomp_rule_name = rule_name.into();
+        self
+    }
+
+    /// Sets a custom seccomp policy, which takes precedence over the seccomp rule.
+    pub fn seccomp_policy(mut self, policy: SeccompPolicy) -> Self {
+        self.config.seccomp_policy = Some(policy);
+        self
+    }
+
+    /// Sets the user and group the program runs as, `0, 0` to run it as root.
+    pub fn user(mut self, uid: u32, gid: u32) -> Self {
+        self.config.uid = uid;
+        self.config.gid = gid;
+        self
+    }
+
+    /// Confines the run in a cgroup with these settings.
+    pub fn cgroup(mut self, cgroup: CgroupConfig) -> Self {
+        self.config.cgroup = Some(cgroup);
+        self
+    }
+
+    /// Sets the namespaces the program is isolated in.
+    pub fn namespaces(mut self, namespaces: Namespaces) -> Self {
+        self.config.namespaces = namespaces;
+        self
+    }
+
+    /// Sets the root filesystem to pivot into.
+    pub fn rootfs(mut self, rootfs: RootFs) -> Self {
+        self.config.rootfs = Some(rootfs);
+        self
+    }
+
+    /// Checks the output against this answer file with `mode`.
+    pub fn answer(mut self, path: impl Into<String>, mode: CheckMode) -> Self {
+        self.config.answer_path = Some(path.into());
+        self.config.check_mode = mode;
+        self
+    }
+
+    /// Checks the output with this special judge.
+    pub fn special_judge(mut self, special_judge: Config) -> Self {
+        self.config.special_judge = Some(Box::new(special_judge));
+        self
+    }
+
+    /// Validates and returns the config.
+    /// # Errors
+    /// Returns the invalid fields, as [`Config::validate`].
+    pub fn build(self) -> Result<Config, Vec<ConfigError>> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
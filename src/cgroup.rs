@@ -0,0 +1,151 @@
+use crate::{ByteSize, Config, MemoryLimitMode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+static CGROUP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// cgroup v2 settings used to confine a run.
+/// A dedicated cgroup is created below `parent` for every run and removed afterwards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CgroupConfig {
+    /// Path of the parent cgroup, e.g. `/sys/fs/cgroup/judger`.
+    /// The `memory`, `pids` and `cpu` controllers must be available to it.
+    pub parent: String,
+    /// CPU bandwidth quota in microseconds per period (`None` for unlimited).
+    /// Serialized with -1 for unlimited.
+    #[serde(with = "crate::limit::legacy::number_or_unlimited")]
+    pub cpu_quota: Option<u64>,
+    /// CPU bandwidth period in microseconds.
+    pub cpu_period: u64,
+}
+
+impl Default for CgroupConfig {
+    fn default() -> Self {
+        CgroupConfig {
+            parent: "/sys/fs/cgroup/judger".to_string(),
+            cpu_quota: Some(100000),
+            cpu_period: 100000,
+        }
+    }
+}
+
+/// Resource usage read back from a cgroup after the run.
+#[derive(Debug, Default)]
+pub(crate) struct CgroupStats {
+    /// Peak memory usage in bytes, if the kernel exposes `memory.peak`.
+    pub memory_peak: Option<ByteSize>,
+    /// Number of processes killed by the OOM killer.
+    pub oom_kills: u64,
+    /// CPU time spent in user mode in microseconds.
+    pub cpu_user: u64,
+    /// CPU time spent in the kernel in microseconds.
+    pub cpu_system: u64,
+}
+
+/// A cgroup created for a single run.
+pub(crate) struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Creates a new cgroup below the configured parent and applies the limits of `config`.
+    pub(crate) fn create(cgroup_config: &CgroupConfig, config: &Config) -> io::Result<Cgroup> {
+        let parent = PathBuf::from(&cgroup_config.parent);
+        fs::create_dir_all(&parent)?;
+        // Controllers may already be enabled, or be managed by the administrator.
+        let _ = fs::write(parent.join("cgroup.subtree_control"), "+memory +pids +cpu");
+
+        let name = format!(
+            "judger-{}-{}",
+            std::process::id(),
+            CGROUP_COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+        let cgroup = Cgroup {
+            path: parent.join(name),
+        };
+        fs::create_dir(&cgroup.path)?;
+        cgroup.apply_limits(cgroup_config, config)?;
+        Ok(cgroup)
+    }
+
+    fn apply_limits(&self, cgroup_config: &CgroupConfig, config: &Config) -> io::Result<()> {
+        if let Some(max_memory) = config.max_memory
+            && config.memory_limit_mode != MemoryLimitMode::CheckOnly
+        {
+            self.write("memory.max", &max_memory.0.to_string())?;
+            self.write("memory.swap.max", "0")?;
+            self.write("memory.oom.group", "1")?;
+        }
+        if let Some(max_process_number) = config.max_process_number {
+            self.write("pids.max", &max_process_number.to_string())?;
+        }
+        if let Some(cpu_quota) = cgroup_config.cpu_quota {
+            self.write(
+                "cpu.max",
+                &format!("{} {}", cpu_quota, cgroup_config.cpu_period),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Moves the calling process into the cgroup.
+    /// Meant to be called by the child between `fork` and `execve`.
+    pub(crate) fn enter(&self) -> io::Result<()> {
+        self.write("cgroup.procs", "0")
+    }
+
+    /// Reads the memory, OOM and CPU statistics of the cgroup.
+    pub(crate) fn stats(&self) -> CgroupStats {
+        let memory_peak = self
+            .read("memory.peak")
+            .ok()
+            .and_then(|peak| peak.trim().parse().ok())
+            .map(ByteSize);
+        CgroupStats {
+            memory_peak,
+            oom_kills: self.read_key("memory.events", "oom_kill").unwrap_or(0),
+            cpu_user: self.read_key("cpu.stat", "user_usec").unwrap_or(0),
+            cpu_system: self.read_key("cpu.stat", "system_usec").unwrap_or(0),
+        }
+    }
+
+    /// Reads the CPU time used so far by the processes in the cgroup, in microseconds.
+    pub(crate) fn cpu_usage(&self) -> Option<u64> {
+        self.read_key("cpu.stat", "usage_usec")
+    }
+
+    fn write(&self, file: &str, value: &str) -> io::Result<()> {
+        fs::write(self.path.join(file), value)
+    }
+
+    fn read(&self, file: &str) -> io::Result<String> {
+        fs::read_to_string(self.path.join(file))
+    }
+
+    /// Reads a value from a flat keyed file such as `memory.events` or `cpu.stat`.
+    fn read_key(&self, file: &str, key: &str) -> Option<u64> {
+        self.read(file).ok()?.lines().find_map(|line| {
+            let (name, value) = line.split_once(' ')?;
+            (name == key).then(|| value.trim().parse().ok()).flatten()
+        })
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // Kill whatever is left (threads, orphaned children) before removing the cgroup.
+        let _ = self.write("cgroup.kill", "1");
+        for _ in 0..100 {
+            if fs::remove_dir(&self.path).is_ok() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
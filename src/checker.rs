@@ -0,0 +1,158 @@
+use crate::ErrorCode;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Longest excerpt of a line or token quoted in a wrong answer summary.
+const MAX_EXCERPT: usize = 32;
+
+/// How the program output is compared with the answer file.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckMode {
+    /// Byte-exact comparison.
+    Exact,
+    /// Line by line, ignoring trailing whitespace on each line and trailing empty lines.
+    #[default]
+    IgnoreTrailingWhitespace,
+    /// Whitespace-separated tokens must be equal.
+    Token,
+    /// Token-wise, but numeric tokens are equal if they differ by at most
+    /// `absolute` or by at most `relative` times the expected value.
+    Float {
+        /// Maximum absolute error.
+        absolute: f64,
+        /// Maximum relative error.
+        relative: f64,
+    },
+}
+
+/// Compares the output file with the answer file.
+/// # Arguments
+/// * `output_path` - Path to the output of the program
+/// * `answer_path` - Path to the expected answer
+/// * `mode` - The comparison mode
+/// # Returns
+/// * `io::Result<ErrorCode>` - `ErrorCode::Success` if the output is accepted,
+///   `ErrorCode::WrongAnswer` with a short summary of the first difference otherwise.
+/// # Errors
+/// Returns an `io::Error` if either file cannot be read.
+pub fn check(output_path: &str, answer_path: &str, mode: CheckMode) -> io::Result<ErrorCode> {
+    let output = std::fs::read(output_path)?;
+    let answer = std::fs::read(answer_path)?;
+    Ok(check_output(&output, &answer, mode))
+}
+
+/// Compares the output with the answer, as [`check`] does with their files.
+pub(crate) fn check_output(output: &[u8], answer: &[u8], mode: CheckMode) -> ErrorCode {
+    let difference = match mode {
+        CheckMode::Exact => compare_exact(output, answer),
+        CheckMode::IgnoreTrailingWhitespace => compare_lines(
+            &String::from_utf8_lossy(output),
+            &String::from_utf8_lossy(answer),
+        ),
+        CheckMode::Token => compare_tokens(
+            &String::from_utf8_lossy(output),
+            &String::from_utf8_lossy(answer),
+            |found, expected| found == expected,
+        ),
+        CheckMode::Float { absolute, relative } => compare_tokens(
+            &String::from_utf8_lossy(output),
+            &String::from_utf8_lossy(answer),
+            |found, expected| match (found.parse::<f64>(), expected.parse::<f64>()) {
+                (Ok(found), Ok(expected)) => {
+                    let error = (found - expected).abs();
+                    error <= absolute || error <= relative * expected.abs()
+                }
+                _ => found == expected,
+            },
+        ),
+    };
+    match difference {
+        Some(summary) => ErrorCode::WrongAnswer(summary),
+        None => ErrorCode::Success,
+    }
+}
+
+fn compare_exact(output: &[u8], answer: &[u8]) -> Option<String> {
+    if let Some(offset) = output.iter().zip(answer).position(|(a, b)| a != b) {
+        return Some(format!("byte {} differs", offset + 1));
+    }
+    if output.len() != answer.len() {
+        return Some(format!(
+            "expected {} bytes, found {}",
+            answer.len(),
+            output.len()
+        ));
+    }
+    None
+}
+
+fn compare_lines(output: &str, answer: &str) -> Option<String> {
+    let output = significant_lines(output);
+    let answer = significant_lines(answer);
+    for (index, (found, expected)) in output.iter().zip(&answer).enumerate() {
+        if found != expected {
+            return Some(format!(
+                "line {}: expected `{}`, found `{}`",
+                index + 1,
+                excerpt(expected),
+                excerpt(found)
+            ));
+        }
+    }
+    if output.len() != answer.len() {
+        return Some(format!(
+            "expected {} lines, found {}",
+            answer.len(),
+            output.len()
+        ));
+    }
+    None
+}
+
+/// Lines with trailing whitespace removed, without the trailing empty lines.
+fn significant_lines(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn compare_tokens<F>(output: &str, answer: &str, equal: F) -> Option<String>
+where
+    F: Fn(&str, &str) -> bool,
+{
+    let mut output = output.split_whitespace();
+    let mut answer = answer.split_whitespace();
+    let mut index = 0;
+    loop {
+        index += 1;
+        match (output.next(), answer.next()) {
+            (Some(found), Some(expected)) if !equal(found, expected) => {
+                return Some(format!(
+                    "token {}: expected `{}`, found `{}`",
+                    index,
+                    excerpt(expected),
+                    excerpt(found)
+                ));
+            }
+            (Some(_), Some(_)) => {}
+            (Some(_), None) => {
+                return Some(format!("expected {} tokens, found more", index - 1));
+            }
+            (None, Some(_)) => {
+                return Some(format!("expected more tokens, found {}", index - 1));
+            }
+            (None, None) => return None,
+        }
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    if text.chars().count() <= MAX_EXCERPT {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(MAX_EXCERPT).collect::<String>())
+    }
+}
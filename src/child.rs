@@ -1,82 +1,107 @@
-use crate::{Config, ErrorCode, LogLevel, Logger, seccomp};
+use crate::{Config, ErrorCode, JudgerError, LogLevel, Logger, namespace, rootfs, seccomp, stdio};
+use clap::ValueEnum;
 use nix::libc;
+use nix::sys::ptrace;
 use nix::sys::resource::{Resource, setrlimit};
 use nix::unistd::{Gid, Uid, execve, setgid, setuid};
+use serde::{Deserialize, Serialize};
 use std::ffi::CString;
-use std::fs::File;
-use std::io::{stderr, stdin, stdout};
+use std::io::{self, stderr, stdin, stdout};
 use std::os::fd::{AsRawFd, RawFd};
 
+/// How the memory limit of a program is enforced.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryLimitMode {
+    /// Limit the address space with `RLIMIT_AS` at twice `max_memory`.
+    /// With a cgroup, `memory.max` is used instead.
+    #[default]
+    Rlimit,
+    /// Only measure the peak memory and report `MemoryLimitExceeded` afterwards.
+    /// Suited to runtimes that reserve large virtual ranges, such as the JVM or Go.
+    CheckOnly,
+    /// Limit the resident memory of the program and its descendants with `memory.max`
+    /// of a per-run cgroup, created with the default [`CgroupConfig`](crate::CgroupConfig)
+    /// if `cgroup` is not set.
+    Cgroup,
+}
+
+/// Wraps the cause of a failed setup step of the child.
+fn failed<E: Into<io::Error>>(stage: ErrorCode) -> impl FnOnce(E) -> JudgerError {
+    move |source| JudgerError::Child {
+        stage,
+        source: source.into(),
+    }
+}
+
 /// Function to be executed in the child process.
-/// Sets resource limits, redirects standard I/O,
+/// Sets resource limits, redirects standard I/O, sets up namespaces and the root filesystem,
 /// changes user and group IDs, loads seccomp rules, and executes the target program.
 /// # Arguments
 /// * `config` - Reference to the configuration struct.
 /// * `logger` - Logger instance for logging errors.
 /// # Returns
-/// * `Result<(), ErrorCode>` - Ok on success, Err with ErrorCode on failure.
+/// * `Result<(), JudgerError>` - Only returns if a step failed, with the step and its cause.
 pub fn child_process(
     config: &Config,
-    mut logger: Logger,
+    logger: Logger,
     fds: Option<(RawFd, RawFd)>,
-) -> Result<(), ErrorCode> {
-    if config.max_stack != -1 {
-        setrlimit(
-            Resource::RLIMIT_STACK,
-            config.max_stack as u64,
-            config.max_stack as u64,
-        )
-        .map_err(|_| ErrorCode::SetrlimitFailed)?;
+) -> Result<(), JudgerError> {
+    let (input, output) = fds.unzip();
+    setup_and_exec(config, logger, [input, output, None], true)
+}
+
+/// [`child_process`], optionally without letting the parent trace seccomp violations.
+/// Only the thread that forked a traced child can resume it, so asynchronous runs do not trace.
+/// Untraced programs are killed by a blocked syscall instead of receiving SIGSYS.
+/// `stdio` replaces the standard input, output and error of the config with pipe ends.
+pub(crate) fn setup_and_exec(
+    config: &Config,
+    mut logger: Logger,
+    stdio: [Option<RawFd>; 3],
+    trace: bool,
+) -> Result<(), JudgerError> {
+    if let Some(max_stack) = config.max_stack {
+        setrlimit(Resource::RLIMIT_STACK, max_stack.0, max_stack.0)
+            .map_err(failed(ErrorCode::SetrlimitFailed))?;
     }
-    if !config.exe_path.contains("java") && config.max_memory != -1 {
-        setrlimit(
-            Resource::RLIMIT_AS,
-            (config.max_memory * 2) as u64,
-            (config.max_memory * 2) as u64,
-        )
-        .map_err(|_| ErrorCode::SetrlimitFailed)?;
+    // With a cgroup, memory and process limits are enforced by `memory.max` and `pids.max`.
+    let use_cgroup = config.cgroup_config().is_some();
+    if !use_cgroup
+        && config.memory_limit_mode == MemoryLimitMode::Rlimit
+        && let Some(max_memory) = config.max_memory
+    {
+        let address_space = max_memory.0.saturating_mul(2);
+        setrlimit(Resource::RLIMIT_AS, address_space, address_space)
+            .map_err(failed(ErrorCode::SetrlimitFailed))?;
     }
-    if config.max_cpu_time != -1 {
-        setrlimit(
-            Resource::RLIMIT_CPU,
-            (config.max_cpu_time / 1000 + 1) as u64,
-            (config.max_cpu_time / 1000 + 1) as u64,
-        )
-        .map_err(|_| ErrorCode::SetrlimitFailed)?;
+    if let Some(max_cpu_time) = config.max_cpu_time {
+        let seconds = max_cpu_time.as_secs().saturating_add(1);
+        setrlimit(Resource::RLIMIT_CPU, seconds, seconds)
+            .map_err(failed(ErrorCode::SetrlimitFailed))?;
     }
-    if config.max_process_number != -1 {
-        setrlimit(
-            Resource::RLIMIT_NPROC,
-            config.max_process_number as u64,
-            config.max_process_number as u64,
-        )
-        .map_err(|_| ErrorCode::SetrlimitFailed)?;
+    if !use_cgroup && let Some(max_process_number) = config.max_process_number {
+        let number = max_process_number.get() as u64;
+        setrlimit(Resource::RLIMIT_NPROC, number, number)
+            .map_err(failed(ErrorCode::SetrlimitFailed))?;
     }
-    if config.max_output_size != -1 {
-        setrlimit(
-            Resource::RLIMIT_FSIZE,
-            config.max_output_size as u64,
-            config.max_output_size as u64,
-        )
-        .map_err(|_| ErrorCode::SetrlimitFailed)?;
+    if let Some(max_output_size) = config.max_output_size {
+        // One byte more than the limit, so that a program ignoring SIGXFSZ still leaves
+        // a file the judger can tell from one of exactly the limit.
+        let max_file_size = max_output_size.0.saturating_add(1);
+        setrlimit(Resource::RLIMIT_FSIZE, max_file_size, max_file_size)
+            .map_err(failed(ErrorCode::SetrlimitFailed))?;
     }
 
-    let (input_fd, output_fd, _input_file, _output_file) = match fds {
-        Some((inf, outf)) => (inf, outf, None, None),
-        None => {
-            let input_file = File::open(&config.input_path).map_err(|_| ErrorCode::Dup2Failed)?;
-            let output_file =
-                File::create(&config.output_path).map_err(|_| ErrorCode::Dup2Failed)?;
-            (
-                input_file.as_raw_fd(),
-                output_file.as_raw_fd(),
-                Some(input_file),
-                Some(output_file),
-            )
-        }
-    };
+    let [input_fd, output_fd, error_fd] = stdio;
+    let input = stdio::open_input(config, input_fd).map_err(failed(ErrorCode::Dup2Failed))?;
+    let output = stdio::open_output(config.stdout.as_ref(), &config.output_path, output_fd)
+        .map_err(failed(ErrorCode::Dup2Failed))?;
+    let error = stdio::open_output(config.stderr.as_ref(), &config.error_path, error_fd)
+        .map_err(failed(ErrorCode::Dup2Failed))?;
 
-    if unsafe { libc::dup2(input_fd, stdin().as_raw_fd()) } == -1 {
+    if unsafe { libc::dup2(input.as_raw_fd(), stdin().as_raw_fd()) } == -1 {
+        let source = io::Error::last_os_error();
         logger
             .write(
                 LogLevel::Fatal,
@@ -84,11 +109,12 @@ pub fn child_process(
                 line!(),
                 format_args!("Error: Failed to redirect standard input."),
             )
-            .map_err(|_| ErrorCode::Dup2Failed)?;
-        return Err(ErrorCode::Dup2Failed);
+            .map_err(JudgerError::Log)?;
+        return Err(failed(ErrorCode::Dup2Failed)(source));
     }
 
-    if unsafe { libc::dup2(output_fd, stdout().as_raw_fd()) } == -1 {
+    if unsafe { libc::dup2(output.as_raw_fd(), stdout().as_raw_fd()) } == -1 {
+        let source = io::Error::last_os_error();
         logger
             .write(
                 LogLevel::Fatal,
@@ -96,12 +122,12 @@ pub fn child_process(
                 line!(),
                 format_args!("Error: Failed to redirect standard output."),
             )
-            .map_err(|_| ErrorCode::Dup2Failed)?;
-        return Err(ErrorCode::Dup2Failed);
+            .map_err(JudgerError::Log)?;
+        return Err(failed(ErrorCode::Dup2Failed)(source));
     }
 
-    let error_file = File::create(&config.error_path).map_err(|_| ErrorCode::Dup2Failed)?;
-    if unsafe { libc::dup2(error_file.as_raw_fd(), stderr().as_raw_fd()) } == -1 {
+    if unsafe { libc::dup2(error.as_raw_fd(), stderr().as_raw_fd()) } == -1 {
+        let source = io::Error::last_os_error();
         logger
             .write(
                 LogLevel::Fatal,
@@ -109,29 +135,92 @@ pub fn child_process(
                 line!(),
                 format_args!("Error: Failed to redirect standard error."),
             )
-            .map_err(|_| ErrorCode::Dup2Failed)?;
-        return Err(ErrorCode::Dup2Failed);
+            .map_err(JudgerError::Log)?;
+        return Err(failed(ErrorCode::Dup2Failed)(source));
     }
 
-    setgid(Gid::from_raw(config.gid)).map_err(|_| ErrorCode::SetuidFailed)?;
-    setuid(Uid::from_raw(config.uid)).map_err(|_| ErrorCode::SetuidFailed)?;
+    let mut namespaces = config.namespaces.clone();
+    // A private root can only be built in a mount namespace of its own.
+    namespaces.mount |= config.rootfs.is_some();
+    if let Err(e) = namespace::setup_child(&namespaces) {
+        logger
+            .write(
+                LogLevel::Fatal,
+                file!(),
+                line!(),
+                format_args!("Error: Failed to set up namespaces: {}", e),
+            )
+            .map_err(JudgerError::Log)?;
+        return Err(failed(ErrorCode::NamespaceFailed)(e));
+    }
+
+    if let Some(root) = &config.rootfs
+        && let Err(e) = rootfs::enter(root, namespaces.pid)
+    {
+        logger
+            .write(
+                LogLevel::Fatal,
+                file!(),
+                line!(),
+                format_args!("Error: Failed to set up the root filesystem: {}", e),
+            )
+            .map_err(JudgerError::Log)?;
+        return Err(failed(ErrorCode::MountFailed)(e));
+    }
 
-    if let Some(rule_name) = &config.seccomp_rule_name {
-        seccomp::load_seccomp_rules(rule_name).map_err(|_| ErrorCode::LoadSeccompFailed)?;
+    setgid(Gid::from_raw(config.gid)).map_err(failed(ErrorCode::SetuidFailed))?;
+    setuid(Uid::from_raw(config.uid)).map_err(failed(ErrorCode::SetuidFailed))?;
+
+    let mut traced = false;
+    if trace && (config.seccomp_policy.is_some() || config.seccomp_rule_name.is_some()) {
+        // Let the parent observe the SIGSYS raised by seccomp so that it can report the syscall.
+        traced = ptrace::traceme().is_ok();
+        if !traced {
+            logger
+                .write(
+                    LogLevel::Warning,
+                    file!(),
+                    line!(),
+                    format_args!(
+                        "Warning: Failed to enable tracing, seccomp violations kill the program."
+                    ),
+                )
+                .map_err(JudgerError::Log)?;
+        }
+    }
+    // Without a tracer, a trapped syscall would raise a SIGSYS the program could catch itself.
+    if let Some(policy) = &config.seccomp_policy {
+        if let Err(e) = policy.load(traced) {
+            logger
+                .write(
+                    LogLevel::Fatal,
+                    file!(),
+                    line!(),
+                    format_args!("Error: {}", e),
+                )
+                .map_err(JudgerError::Log)?;
+            return Err(failed(ErrorCode::LoadSeccompFailed)(io::Error::other(e)));
+        }
+    } else if let Some(rule_name) = &config.seccomp_rule_name {
+        seccomp::load_seccomp_rules(rule_name, traced).map_err(JudgerError::Seccomp)?;
     }
 
     if let Ok(exe_path) = CString::new(config.exe_path.clone()) {
-        let args: Vec<CString> = config
-            .args
-            .iter()
-            .map(|arg| CString::new(arg.as_str()).unwrap_or_default())
-            .collect();
+        let args: Vec<CString> = if config.args.is_empty() {
+            vec![exe_path.clone()]
+        } else {
+            config
+                .args
+                .iter()
+                .map(|arg| CString::new(arg.as_str()).unwrap_or_default())
+                .collect()
+        };
         let env: Vec<CString> = config
             .env
             .iter()
             .map(|e| CString::new(e.as_str()).unwrap_or_default())
             .collect();
-        execve(&exe_path, &args, &env).map_err(|_| ErrorCode::ExecveFailed)?;
+        execve(&exe_path, &args, &env).map_err(failed(ErrorCode::ExecveFailed))?;
     } else {
         logger
             .write(
@@ -140,8 +229,10 @@ pub fn child_process(
                 line!(),
                 format_args!("Error: Invalid executable path."),
             )
-            .map_err(|_| ErrorCode::ExecveFailed)?;
-        return Err(ErrorCode::ExecveFailed);
+            .map_err(JudgerError::Log)?;
+        return Err(failed(ErrorCode::ExecveFailed)(io::Error::from(
+            io::ErrorKind::InvalidInput,
+        )));
     }
     Ok(())
 }
@@ -0,0 +1,35 @@
+use crate::{Config, ErrorCode, JudgerError, RunResult, run};
+use std::fs::File;
+use std::io::Read;
+
+/// Maximum number of bytes of compiler diagnostics kept in [`ErrorCode::CompileError`].
+pub const MAX_COMPILE_DIAGNOSTICS: u64 = 64 * 1024;
+
+/// Runs a compiler inside the sandbox.
+/// `config` describes the compiler invocation, usually based on [`Config::compiler`].
+/// If the compiler exits with a non-zero code, the result is [`ErrorCode::CompileError`]
+/// with the diagnostics written to `error_path` (or `output_path` if that is empty),
+/// truncated to [`MAX_COMPILE_DIAGNOSTICS`] bytes. Limit verdicts are reported unchanged.
+/// # Arguments
+/// * `config` - A reference to the `Config` struct describing the compiler run
+/// # Returns
+/// * `Result<RunResult, JudgerError>` - The outcome of the compiler, or the failure of the sandbox.
+pub fn compile(config: &Config) -> Result<RunResult, JudgerError> {
+    let mut result = run(config, None)?;
+    if result.result == ErrorCode::RuntimeError && result.signal == 0 {
+        let mut diagnostics = read_diagnostics(&config.error_path);
+        if diagnostics.is_empty() {
+            diagnostics = read_diagnostics(&config.output_path);
+        }
+        result.result = ErrorCode::CompileError(diagnostics);
+    }
+    Ok(result)
+}
+
+fn read_diagnostics(path: &str) -> String {
+    let mut buffer = Vec::new();
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(MAX_COMPILE_DIAGNOSTICS).read_to_end(&mut buffer);
+    }
+    String::from_utf8_lossy(&buffer).into_owned()
+}
@@ -1,18 +1,23 @@
+use libseccomp::error::SeccompError;
+use nix::errno::Errno;
 use serde::Serialize;
 use std::fmt::Display;
+use std::io;
 
 /// Error codes for the judger.
+/// Codes from `InvalidConfig` to `MountFailed` describe failures of the sandbox and are
+/// reported through [`JudgerError`], the others are verdicts found in `RunResult::result`.
 #[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
 pub enum ErrorCode {
-    /// Operation completed successfully.
+    /// Operation completed successfully. When the output was checked, it was accepted.
     #[default]
     Success,
     /// Configuration is invalid.
     InvalidConfig,
     /// Forking a new process failed.
     ForkFailed,
-    /// Compiling the source code failed.
-    CompileError,
+    /// Compiling the source code failed, with the compiler diagnostics.
+    CompileError(String),
     /// Waiting for a process failed.
     WaitFailed,
     /// Root privileges are required.
@@ -27,10 +32,16 @@ pub enum ErrorCode {
     SetuidFailed,
     /// Executing the target program failed.
     ExecveFailed,
-    /// Special judge program error.
+    /// The special judge or the interactor crashed, exceeded its limits or reported a failure.
     SpjError,
     /// System error
     SystemError,
+    /// Setting up the cgroup failed.
+    CgroupFailed,
+    /// Setting up the namespaces failed.
+    NamespaceFailed,
+    /// Setting up the root filesystem failed.
+    MountFailed,
     /// Cpu time limit exceeded
     CpuTimeLimitExceeded,
     /// Real time limit exceeded
@@ -39,27 +50,59 @@ pub enum ErrorCode {
     MemoryLimitExceeded,
     /// Runtime error
     RuntimeError,
-    /// Interactor produced wrong answer
+    /// Output limit exceeded
+    OutputLimitExceeded,
+    /// A syscall forbidden by the seccomp rules was made
+    SyscallViolation,
+    /// Wrong answer reported by the checker or the interactor
     WrongAnswer(String),
+    /// Presentation error reported by the checker
+    PresentationError(String),
+    /// Partial points awarded by the checker, with its message
+    PartiallyCorrect(String),
+    /// The run was cancelled through its [`RunHandle`](crate::RunHandle) before it finished
+    Cancelled,
 }
 
 impl Display for ErrorCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ErrorCode::WrongAnswer(msg) => write!(f, "Wrong Answer: {}", msg),
+            ErrorCode::CompileError(msg) => write!(f, "Compile Error: {}", msg),
+            ErrorCode::PresentationError(msg) => write!(f, "Presentation Error: {}", msg),
+            ErrorCode::PartiallyCorrect(msg) => write!(f, "Partially Correct: {}", msg),
             _ => write!(f, "{:?}", self),
         }
     }
 }
 
 impl ErrorCode {
+    /// Maps the code of a sandbox setup step back to the `ErrorCode`.
+    fn from_setup_code(code: i32) -> Option<ErrorCode> {
+        [
+            ErrorCode::InvalidConfig,
+            ErrorCode::RootRequired,
+            ErrorCode::LoadSeccompFailed,
+            ErrorCode::SetrlimitFailed,
+            ErrorCode::Dup2Failed,
+            ErrorCode::SetuidFailed,
+            ErrorCode::ExecveFailed,
+            ErrorCode::SystemError,
+            ErrorCode::CgroupFailed,
+            ErrorCode::NamespaceFailed,
+            ErrorCode::MountFailed,
+        ]
+        .into_iter()
+        .find(|stage| stage.to_i32() == code)
+    }
+
     /// Convert the ErrorCode to its corresponding i32 value.
     pub fn to_i32(&self) -> i32 {
         match self {
             ErrorCode::Success => 0,
             ErrorCode::InvalidConfig => -1,
             ErrorCode::ForkFailed => -2,
-            ErrorCode::CompileError => -3,
+            ErrorCode::CompileError(_) => -3,
             ErrorCode::WaitFailed => -4,
             ErrorCode::RootRequired => -5,
             ErrorCode::LoadSeccompFailed => -6,
@@ -69,11 +112,149 @@ impl ErrorCode {
             ErrorCode::ExecveFailed => -10,
             ErrorCode::SpjError => -11,
             ErrorCode::SystemError => -12,
+            ErrorCode::CgroupFailed => -13,
+            ErrorCode::NamespaceFailed => -14,
+            ErrorCode::MountFailed => -15,
             ErrorCode::CpuTimeLimitExceeded => 1,
             ErrorCode::RealTimeLimitExceeded => 2,
             ErrorCode::MemoryLimitExceeded => 3,
             ErrorCode::RuntimeError => 4,
             ErrorCode::WrongAnswer(_) => 5,
+            ErrorCode::OutputLimitExceeded => 6,
+            ErrorCode::SyscallViolation => 7,
+            ErrorCode::PresentationError(_) => 8,
+            ErrorCode::PartiallyCorrect(_) => 9,
+            ErrorCode::Cancelled => 10,
         }
     }
 }
+
+/// Failure of the sandbox itself, as opposed to a verdict on the program in `RunResult::result`.
+#[derive(Debug)]
+pub enum JudgerError {
+    /// The configuration is invalid.
+    InvalidConfig(String),
+    /// Root privileges are required.
+    RootRequired,
+    /// Opening or writing the log file failed.
+    Log(io::Error),
+    /// A file operation of the supervisor failed.
+    Io {
+        /// Step of the run that failed.
+        stage: ErrorCode,
+        /// Cause of the failure.
+        source: io::Error,
+    },
+    /// A system call of the supervisor failed.
+    Sys {
+        /// Step of the run that failed.
+        stage: ErrorCode,
+        /// Cause of the failure.
+        source: Errno,
+    },
+    /// Setting up the sandbox in the child failed before the program was executed.
+    Child {
+        /// Step of the setup that failed.
+        stage: ErrorCode,
+        /// Cause of the failure.
+        source: io::Error,
+    },
+    /// Loading the seccomp rules failed.
+    Seccomp(SeccompError),
+    /// The problem package is broken.
+    InvalidProblem(String),
+}
+
+impl JudgerError {
+    /// The error code of the failed step, also the exit code of a child that failed it.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            JudgerError::InvalidConfig(_) | JudgerError::InvalidProblem(_) => {
+                ErrorCode::InvalidConfig
+            }
+            JudgerError::RootRequired => ErrorCode::RootRequired,
+            JudgerError::Log(_) => ErrorCode::SystemError,
+            JudgerError::Io { stage, .. }
+            | JudgerError::Sys { stage, .. }
+            | JudgerError::Child { stage, .. } => stage.clone(),
+            JudgerError::Seccomp(_) => ErrorCode::LoadSeccompFailed,
+        }
+    }
+
+    /// Encodes an error of the child for the parent: the stage code, the errno
+    /// (0 if there is none) and the message.
+    pub(crate) fn to_report(&self) -> Vec<u8> {
+        let errno = match self {
+            JudgerError::Log(source)
+            | JudgerError::Io { source, .. }
+            | JudgerError::Child { source, .. } => source.raw_os_error().unwrap_or(0),
+            JudgerError::Sys { source, .. } => *source as i32,
+            JudgerError::Seccomp(source) => source.sysrawrc().map_or(0, |rc| -rc),
+            _ => 0,
+        };
+        let mut report = Vec::new();
+        report.extend_from_slice(&self.code().to_i32().to_ne_bytes());
+        report.extend_from_slice(&errno.to_ne_bytes());
+        report.extend_from_slice(self.to_string().as_bytes());
+        report
+    }
+
+    /// Decodes a report written by [`JudgerError::to_report`].
+    pub(crate) fn from_report(report: &[u8]) -> Option<JudgerError> {
+        let code = i32::from_ne_bytes(report.get(0..4)?.try_into().ok()?);
+        let errno = i32::from_ne_bytes(report.get(4..8)?.try_into().ok()?);
+        let stage = ErrorCode::from_setup_code(code)?;
+        let source = if errno != 0 {
+            io::Error::from_raw_os_error(errno)
+        } else {
+            io::Error::other(String::from_utf8_lossy(&report[8..]).into_owned())
+        };
+        Some(JudgerError::Child { stage, source })
+    }
+}
+
+impl Display for JudgerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JudgerError::InvalidConfig(msg) => write!(f, "Invalid configuration: {}", msg),
+            JudgerError::RootRequired => write!(f, "Root privileges are required"),
+            JudgerError::Log(e) => write!(f, "Failed to write to log file: {}", e),
+            JudgerError::Io { stage, source } | JudgerError::Child { stage, source } => {
+                write!(f, "{}: {}", stage, source)
+            }
+            JudgerError::Sys { stage, source } => write!(f, "{}: {}", stage, source),
+            JudgerError::Seccomp(e) => write!(f, "Failed to load seccomp rules: {}", e),
+            JudgerError::InvalidProblem(msg) => write!(f, "Invalid problem package: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for JudgerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JudgerError::Log(source)
+            | JudgerError::Io { source, .. }
+            | JudgerError::Child { source, .. } => Some(source),
+            JudgerError::Sys { source, .. } => Some(source),
+            JudgerError::Seccomp(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// A field of a [`Config`](crate::Config) that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Name of the field. Fields of nested configs are prefixed, as in `special_judge.exe_path`.
+    pub field: String,
+    /// What is wrong with the value.
+    pub message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,141 @@
+use crate::runner::{RunControl, run_controlled};
+use crate::{Config, ErrorCode, JudgerError, LogLevel, Logger, RunResult};
+use nix::unistd::Uid;
+use std::io;
+use std::sync::{Arc, mpsc};
+use std::thread::{self, JoinHandle};
+
+/// A run started by [`spawn`], supervised by a thread of its own.
+/// Dropping the handle detaches the run, which then finishes unobserved.
+#[derive(Debug)]
+pub struct RunHandle {
+    pid: i32,
+    control: Arc<RunControl>,
+    thread: Option<JoinHandle<Result<RunResult, JudgerError>>>,
+    /// Result collected by [`RunHandle::try_wait`].
+    result: Option<RunResult>,
+}
+
+/// Starts the judger with the given configuration and returns without waiting for it.
+/// The run is the same as with [`run`](crate::run), but it can be cancelled through the handle.
+/// # Arguments
+/// * `config` - A reference to the `Config` struct containing the judger configuration
+/// * `interactor` - An optional `Config` for a testlib interactor, as for [`run`](crate::run)
+/// # Returns
+/// * `Result<RunHandle, JudgerError>` - The handle of the forked program.
+///   `Err` means the sandbox failed before the program was forked.
+pub fn spawn(config: &Config, interactor: Option<&Config>) -> Result<RunHandle, JudgerError> {
+    if !Uid::current().is_root() {
+        let mut logger = Logger::new(&config.log_path).map_err(JudgerError::Log)?;
+        logger
+            .write(
+                LogLevel::Fatal,
+                file!(),
+                line!(),
+                format_args!("Error: Root privileges are required to run the judger."),
+            )
+            .map_err(JudgerError::Log)?;
+        return Err(JudgerError::RootRequired);
+    }
+
+    let (started, pid) = mpsc::channel();
+    let control = Arc::new(RunControl::new(started));
+    let thread = {
+        let config = config.clone();
+        let interactor = interactor.cloned();
+        let control = Arc::clone(&control);
+        thread::Builder::new()
+            .name("judger-run".to_string())
+            .spawn(move || {
+                let result = run_controlled(&config, interactor.as_ref(), &control);
+                control.close();
+                result
+            })
+            .map_err(|source| JudgerError::Io {
+                stage: ErrorCode::SystemError,
+                source,
+            })?
+    };
+    match pid.recv() {
+        Ok(pid) => Ok(RunHandle {
+            pid: pid.as_raw(),
+            control,
+            thread: Some(thread),
+            result: None,
+        }),
+        // The run failed before the program was forked.
+        Err(_) => match join(thread) {
+            Err(e) => Err(e),
+            Ok(_) => Err(JudgerError::Io {
+                stage: ErrorCode::ForkFailed,
+                source: io::Error::other("the program was not started"),
+            }),
+        },
+    }
+}
+
+impl RunHandle {
+    /// PID of the program, as seen from the judger.
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// Kills the program, and its interactor or special judge, if they are still running.
+    /// The run then ends with [`ErrorCode::Cancelled`]. A run that already finished keeps its result.
+    pub fn kill(&self) {
+        self.control.cancel();
+    }
+
+    /// Waits for the run to finish and returns its result.
+    /// # Errors
+    /// Returns the [`JudgerError`] the sandbox failed with.
+    pub fn wait(mut self) -> Result<RunResult, JudgerError> {
+        if let Some(result) = self.result.take() {
+            return Ok(result);
+        }
+        match self.thread.take() {
+            Some(thread) => join(thread),
+            None => Err(collected()),
+        }
+    }
+
+    /// Returns the result if the run has finished, without blocking.
+    /// Once it has returned the result, later calls and [`RunHandle::wait`] return it again.
+    /// # Errors
+    /// Returns the [`JudgerError`] the sandbox failed with, once.
+    pub fn try_wait(&mut self) -> Result<Option<RunResult>, JudgerError> {
+        if let Some(result) = &self.result {
+            return Ok(Some(result.clone()));
+        }
+        match self.thread.take() {
+            Some(thread) if thread.is_finished() => {
+                let result = join(thread)?;
+                self.result = Some(result.clone());
+                Ok(Some(result))
+            }
+            Some(thread) => {
+                self.thread = Some(thread);
+                Ok(None)
+            }
+            None => Err(collected()),
+        }
+    }
+}
+
+/// Joins the thread supervising a run.
+fn join(thread: JoinHandle<Result<RunResult, JudgerError>>) -> Result<RunResult, JudgerError> {
+    thread.join().unwrap_or_else(|_| {
+        Err(JudgerError::Io {
+            stage: ErrorCode::SystemError,
+            source: io::Error::other("run thread panicked"),
+        })
+    })
+}
+
+/// Error of a run whose failure was already returned by [`RunHandle::try_wait`].
+fn collected() -> JudgerError {
+    JudgerError::Io {
+        stage: ErrorCode::WaitFailed,
+        source: io::Error::other("the failure of the run was already returned"),
+    }
+}
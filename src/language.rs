@@ -0,0 +1,248 @@
+use crate::{Config, MemoryLimitMode, SeccompRuleName};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// How programs in a language are compiled and run.
+/// In `compile` and `run`, `{source}` is replaced by the source file and `{exe}` by the compiled program.
+/// # Example
+/// ```toml
+/// [[languages]]
+/// name = "pascal"
+/// compile = ["/usr/bin/fpc", "-O2", "-o{exe}", "{source}"]
+/// run = ["{exe}"]
+/// seccomp_rule_name = "c-cpp"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageProfile {
+    /// Name the language is selected by.
+    pub name: String,
+    /// Compiler command line (empty for interpreted languages).
+    #[serde(default)]
+    pub compile: Vec<String>,
+    /// Command line running the program. The first element is the executable.
+    pub run: Vec<String>,
+    /// Environment variables for compiling and running. When compiling they are added to
+    /// the `env` of [`Config::compiler`], replacing variables of the same name.
+    #[serde(default)]
+    pub env: Vec<String>,
+    /// Seccomp rule applied to the program.
+    pub seccomp_rule_name: Option<SeccompRuleName>,
+    /// How the memory limit of the program is enforced. Compilers keep the mode of
+    /// [`Config::compiler`].
+    #[serde(default)]
+    pub memory_limit_mode: MemoryLimitMode,
+    /// Factor applied to the time limits.
+    #[serde(default = "default_time_multiplier")]
+    pub time_multiplier: f64,
+}
+
+fn default_time_multiplier() -> f64 {
+    1.0
+}
+
+/// Layout of a file of user-defined language profiles.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguageFile {
+    languages: Vec<LanguageProfile>,
+}
+
+impl LanguageProfile {
+    fn new(name: &str, compile: &[&str], run: &[&str], seccomp_rule_name: SeccompRuleName) -> Self {
+        LanguageProfile {
+            name: name.to_string(),
+            compile: compile.iter().map(|arg| arg.to_string()).collect(),
+            run: run.iter().map(|arg| arg.to_string()).collect(),
+            env: vec![],
+            seccomp_rule_name: Some(seccomp_rule_name),
+            memory_limit_mode: MemoryLimitMode::default(),
+            time_multiplier: default_time_multiplier(),
+        }
+    }
+
+    /// Config compiling `source` to `exe` with the limits of [`Config::compiler`],
+    /// or `None` if the language is not compiled.
+    pub fn compile_config(&self, source: &str, exe: &str) -> Option<Config> {
+        if self.compile.is_empty() {
+            return None;
+        }
+        let args = expand(&self.compile, source, exe);
+        let compiler = Config::compiler();
+        let name = |var: &str| var.split('=').next().unwrap_or_default().to_string();
+        let overridden: Vec<String> = self.env.iter().map(|var| name(var)).collect();
+        let env = compiler
+            .env
+            .iter()
+            .filter(|var| !overridden.contains(&name(var)))
+            .chain(&self.env)
+            .cloned()
+            .collect();
+        Some(Config {
+            exe_path: args.first().cloned().unwrap_or_default(),
+            args,
+            env,
+            ..compiler
+        })
+    }
+
+    /// Config running the program built from `source`, with the limits of `limits`
+    /// scaled by the time multiplier.
+    pub fn run_config(&self, source: &str, exe: &str, limits: Config) -> Config {
+        let args = expand(&self.run, source, exe);
+        let scale = |limit: Option<Duration>| {
+            limit.map(|limit| {
+                Duration::from_millis(
+                    (limit.as_millis() as f64 * self.time_multiplier).ceil() as u64
+                )
+            })
+        };
+        Config {
+            max_cpu_time: scale(limits.max_cpu_time),
+            max_real_time: scale(limits.max_real_time),
+            exe_path: args.first().cloned().unwrap_or_default(),
+            args,
+            env: self.env.clone(),
+            seccomp_rule_name: self.seccomp_rule_name.clone(),
+            memory_limit_mode: self.memory_limit_mode,
+            ..limits
+        }
+    }
+}
+
+fn expand(template: &[String], source: &str, exe: &str) -> Vec<String> {
+    template
+        .iter()
+        .map(|arg| arg.replace("{source}", source).replace("{exe}", exe))
+        .collect()
+}
+
+/// A set of language profiles, looked up by name.
+#[derive(Debug, Clone)]
+pub struct LanguageRegistry {
+    profiles: Vec<LanguageProfile>,
+}
+
+impl Default for LanguageRegistry {
+    /// Built-in profiles for `c`, `cpp`, `java`, `python`, `go`, `node` and `rust`.
+    fn default() -> Self {
+        let managed_runtime = |profile: LanguageProfile, time_multiplier: f64| LanguageProfile {
+            // Runtimes reserve far more address space than they use.
+            memory_limit_mode: MemoryLimitMode::CheckOnly,
+            time_multiplier,
+            ..profile
+        };
+        LanguageRegistry {
+            profiles: vec![
+                LanguageProfile::new(
+                    "c",
+                    &["/usr/bin/gcc", "-O2", "-o", "{exe}", "{source}", "-lm"],
+                    &["{exe}"],
+                    SeccompRuleName::CCpp,
+                ),
+                LanguageProfile::new(
+                    "cpp",
+                    &[
+                        "/usr/bin/g++",
+                        "-O2",
+                        "-std=c++17",
+                        "-o",
+                        "{exe}",
+                        "{source}",
+                    ],
+                    &["{exe}"],
+                    SeccompRuleName::CCpp,
+                ),
+                managed_runtime(
+                    LanguageProfile::new(
+                        "java",
+                        &[],
+                        &[
+                            "/usr/bin/java",
+                            "-Xmx256M",
+                            "-XX:CompressedClassSpaceSize=64M",
+                            "{source}",
+                        ],
+                        SeccompRuleName::Java,
+                    ),
+                    2.0,
+                ),
+                LanguageProfile {
+                    time_multiplier: 2.0,
+                    ..LanguageProfile::new(
+                        "python",
+                        &[],
+                        &["/usr/bin/python3", "{source}"],
+                        SeccompRuleName::Python,
+                    )
+                },
+                managed_runtime(
+                    LanguageProfile {
+                        env: vec![
+                            "PATH=/usr/local/go/bin:/usr/bin:/bin".to_string(),
+                            "HOME=/tmp".to_string(),
+                            "GOCACHE=/tmp/judger-go-cache".to_string(),
+                        ],
+                        ..LanguageProfile::new(
+                            "go",
+                            &["/usr/bin/go", "build", "-o", "{exe}", "{source}"],
+                            &["{exe}"],
+                            SeccompRuleName::Golang,
+                        )
+                    },
+                    1.0,
+                ),
+                managed_runtime(
+                    LanguageProfile::new(
+                        "node",
+                        &[],
+                        &["/usr/bin/node", "{source}"],
+                        SeccompRuleName::Node,
+                    ),
+                    2.0,
+                ),
+                LanguageProfile::new(
+                    "rust",
+                    &["/usr/bin/rustc", "-O", "-o", "{exe}", "{source}"],
+                    &["{exe}"],
+                    SeccompRuleName::General,
+                ),
+            ],
+        }
+    }
+}
+
+impl LanguageRegistry {
+    /// Looks up a profile by name.
+    pub fn get(&self, name: &str) -> Option<&LanguageProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Adds a profile, replacing any profile with the same name.
+    pub fn insert(&mut self, profile: LanguageProfile) {
+        self.profiles
+            .retain(|existing| existing.name != profile.name);
+        self.profiles.push(profile);
+    }
+
+    /// Adds the profiles listed under `languages` in a file.
+    /// Files ending in `.toml` are parsed as TOML, anything else as JSON.
+    /// # Errors
+    /// Returns a message if the file cannot be read or parsed.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read language profiles: {}", e))?;
+        let file: LanguageFile = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&content).map_err(|e| format!("Invalid language profiles: {}", e))?
+        } else {
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid language profiles: {}", e))?
+        };
+        for profile in file.languages {
+            self.insert(profile);
+        }
+        Ok(())
+    }
+}
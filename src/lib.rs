@@ -12,37 +12,69 @@
 //! - Error handling with specific error codes
 //! # Example
 //! ```rust
-//!  use judger::{Config, SeccompRuleName, run};
+//!  use judger::{ByteSize, CheckMode, Config, MemoryLimitMode, Namespaces, SeccompRuleName, run};
+//!  use std::num::NonZeroU32;
+//!  use std::time::Duration;
 //!  let config = Config {
-//!     max_cpu_time: 1000,
-//!     max_real_time: 2000,
-//!     max_memory: 128 * 1024 * 1024,
-//!     max_stack: 32 * 1024 * 1024,
-//!     max_process_number: 1,
-//!     max_output_size: 10000,
+//!     max_cpu_time: Some(Duration::from_secs(1)),
+//!     cpu_time_grace: Duration::from_millis(50),
+//!     include_descendant_cpu_time: false,
+//!     max_real_time: Some(Duration::from_secs(2)),
+//!     max_memory: Some(ByteSize::mib(128)),
+//!     memory_limit_mode: MemoryLimitMode::Rlimit,
+//!     max_stack: Some(ByteSize::mib(32)),
+//!     max_process_number: NonZeroU32::new(1),
+//!     max_output_size: Some(ByteSize(10000)),
 //!     exe_path: "hello_world".to_string(),
 //!     input_path: "1.in".to_string(),
 //!     output_path: "1.out".to_string(),
 //!     error_path: "1.err".to_string(),
+//!     stdin: None,
+//!     stdout: None,
+//!     stderr: None,
 //!     args: vec![],
 //!     env: vec![],
 //!     log_path: "judger.log".to_string(),
 //!     seccomp_rule_name: Some(SeccompRuleName::CCpp),
+//!     seccomp_policy: None,
 //!     uid: 0,
 //!     gid: 0,
+//!     cgroup: None,
+//!     namespaces: Namespaces::default(),
+//!     rootfs: None,
+//!     answer_path: None,
+//!     check_mode: CheckMode::default(),
+//!     special_judge: None,
 //!  };
 //!  let result = run(&config, None);
 //!  println!("{:?}", result);
 //! ```
 //! # Modules
+//! - `async_runner`: Supervises runs on the tokio runtime (`async` feature).
+//! - `batch`: Runs a program against many test cases.
+//! - `builder`: Builds a `Config` from typed limits.
+//! - `cgroup`: Implements the optional cgroup v2 resource backend.
+//! - `checker`: Compares the program output with the expected answer.
 //! - `child`: Handles the child process execution and resource limiting.
+//! - `compiler`: Runs compilers in the sandbox and reports compile errors.
+//! - `handle`: Starts runs in the background and cancels them.
+//! - `language`: Describes how each language is compiled and run.
+//! - `limit`: Typed resource limits and the legacy integer layout.
 //! - `logger`: Provides logging functionalities.
+//! - `namespace`: Isolates the child in Linux namespaces.
+//! - `problem`: Loads problem packages and judges submissions against them.
+//! - `rootfs`: Confines the child to a read-only root filesystem.
 //! - `runner`: Manages the overall execution flow.
+//! - `scoring`: Scores subtasks from per-test results.
 //! - `seccomp`: Implements seccomp filtering.
+//! - `seccomp_policy`: Compiles declarative seccomp policies loaded from JSON or TOML.
+//! - `stdio`: Connects standard streams to files, descriptors or in-memory buffers.
+//! - `spj`: Runs testlib-style special judges.
 //! - `utils`: Contains utility functions and error codes.
 //! # Error Handling
-//! The library defines a set of error codes in the `utils` module to represent various failure scenarios.
-//! Users can handle these errors appropriately based on their needs.
+//! Verdicts of the program, such as `WrongAnswer` or `CpuTimeLimitExceeded`, are reported as an
+//! [`ErrorCode`] in [`RunResult::result`]. Failures of the sandbox itself are returned as a
+//! [`JudgerError`], which names the failed stage and keeps the underlying error as its source.
 //! # Logging
 //! The `logger` module provides a simple logging mechanism with different log levels.
 //! Users can log messages to a specified log file for debugging and monitoring purposes.
@@ -57,35 +89,101 @@
 //! # Author
 //! Developed by [harkerhand](https://github.com/harkerhand).
 
+#[cfg(feature = "async")]
+mod async_runner;
+mod batch;
+mod builder;
+mod cgroup;
+mod checker;
 mod child;
+mod compiler;
 mod error;
+mod handle;
+mod language;
+mod limit;
 mod logger;
+mod namespace;
+mod problem;
+mod rootfs;
 mod runner;
+mod scoring;
 mod seccomp;
+mod seccomp_policy;
+mod spj;
+mod stdio;
 
-pub use child::child_process;
-pub use error::ErrorCode;
+#[cfg(feature = "async")]
+pub use async_runner::run_async;
+pub use batch::{BatchOptions, TestCase, run_batch};
+pub use builder::ConfigBuilder;
+pub use cgroup::CgroupConfig;
+pub use checker::{CheckMode, check};
+pub use child::{MemoryLimitMode, child_process};
+pub use compiler::{MAX_COMPILE_DIAGNOSTICS, compile};
+pub use error::{ConfigError, ErrorCode, JudgerError};
+pub use handle::{RunHandle, spawn};
+pub use language::{LanguageProfile, LanguageRegistry};
+pub use limit::{ByteSize, LegacyLimits};
 pub use logger::LogLevel;
 pub use logger::Logger;
+pub use namespace::Namespaces;
+pub use problem::{JudgeReport, LimitOverride, Problem, judge};
+pub use rootfs::BindMount;
+pub use rootfs::RootFs;
 pub use runner::RunResult;
 pub use runner::run;
+pub use scoring::{
+    Aggregation, ScoreReport, Scoring, Subtask, SubtaskReport, SubtaskStatus, TestReport,
+};
 pub use seccomp::SeccompRuleName;
+pub use seccomp_policy::{ArgCondition, CompareOp, PolicyAction, PolicyRule, SeccompPolicy};
+pub use stdio::{InputSource, OutputSink};
+
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::Duration;
+
+/// JSON Schema of [`Config`] as read by [`Config::from_file`] and `judger run --config`.
+pub const CONFIG_SCHEMA: &str = include_str!("../assets/config.schema.json");
 
 /// Configuration for the judger.
-#[derive(Debug, Clone)]
+/// When deserialized, missing fields take their values from [`Config::default`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
-    /// Maximum CPU time in milliseconds (-1 for unlimited).
-    pub max_cpu_time: i32,
-    /// Maximum real time in milliseconds (-1 for unlimited).
-    pub max_real_time: i32,
-    /// Maximum memory in bytes (-1 for unlimited).
-    pub max_memory: i64,
-    /// Maximum stack size in bytes.
-    pub max_stack: i64,
-    /// Maximum number of processes (-1 for unlimited).
-    pub max_process_number: i32,
-    /// Maximum output size in bytes (-1 for unlimited).
-    pub max_output_size: i64,
+    /// Maximum CPU time (`None` for unlimited). Serialized in milliseconds, -1 for unlimited.
+    #[serde(with = "limit::legacy::millis_or_unlimited")]
+    pub max_cpu_time: Option<Duration>,
+    /// CPU time the program may use beyond `max_cpu_time` before it is killed.
+    /// It is still judged against `max_cpu_time` alone. Serialized in milliseconds.
+    #[serde(with = "limit::legacy::duration_millis")]
+    pub cpu_time_grace: Duration,
+    /// Whether `cpu_time` also counts the descendants of the program. Otherwise only the
+    /// program process itself is timed. Descendants the program did not wait for are only
+    /// counted with a cgroup.
+    pub include_descendant_cpu_time: bool,
+    /// Maximum real time (`None` for unlimited). Serialized in milliseconds, -1 for unlimited.
+    #[serde(with = "limit::legacy::millis_or_unlimited")]
+    pub max_real_time: Option<Duration>,
+    /// Maximum memory (`None` for unlimited). Serialized in bytes, -1 for unlimited.
+    #[serde(with = "limit::legacy::bytes_or_unlimited")]
+    pub max_memory: Option<ByteSize>,
+    /// How `max_memory` is enforced.
+    pub memory_limit_mode: MemoryLimitMode,
+    /// Maximum stack size (`None` for unlimited). Serialized in bytes, -1 for unlimited.
+    #[serde(with = "limit::legacy::bytes_or_unlimited")]
+    pub max_stack: Option<ByteSize>,
+    /// Maximum number of processes (`None` for unlimited). Serialized with -1 for unlimited.
+    #[serde(with = "limit::legacy::count_or_unlimited")]
+    pub max_process_number: Option<NonZeroU32>,
+    /// Maximum output size (`None` for unlimited). Serialized in bytes, -1 for unlimited.
+    /// Every file the program writes is capped with `RLIMIT_FSIZE`, but only the output and
+    /// error files and captured streams count towards `OutputLimitExceeded`. Files the program
+    /// creates itself are cut at the limit without a verdict.
+    #[serde(with = "limit::legacy::bytes_or_unlimited")]
+    pub max_output_size: Option<ByteSize>,
     /// Path to the executable.
     pub exe_path: String,
     /// Path to the input file.
@@ -94,7 +192,14 @@ pub struct Config {
     pub output_path: String,
     /// Path to the error file.
     pub error_path: String,
-    /// Arguments to pass to the executable.
+    /// Source of the standard input. Takes precedence over `input_path` when set.
+    pub stdin: Option<InputSource>,
+    /// Destination of the standard output. Takes precedence over `output_path` when set.
+    pub stdout: Option<OutputSink>,
+    /// Destination of the standard error. Takes precedence over `error_path` when set.
+    pub stderr: Option<OutputSink>,
+    /// Arguments to pass to the executable, starting with `argv[0]`.
+    /// If empty, the program gets `exe_path` as its only argument.
     pub args: Vec<String>,
     /// Environment variables for the executable.
     pub env: Vec<String>,
@@ -102,42 +207,252 @@ pub struct Config {
     pub log_path: String,
     /// Name of the seccomp rule to apply.
     pub seccomp_rule_name: Option<SeccompRuleName>,
+    /// Custom seccomp policy. Takes precedence over `seccomp_rule_name` when set.
+    pub seccomp_policy: Option<SeccompPolicy>,
     /// User ID to run the process as.
     pub uid: u32,
     /// Group ID to run the process as.
     pub gid: u32,
+    /// cgroup v2 settings. When set, memory, process and CPU limits are enforced
+    /// through a per-run cgroup instead of `setrlimit`.
+    /// [`MemoryLimitMode::Cgroup`] uses the default settings if this is not set.
+    pub cgroup: Option<CgroupConfig>,
+    /// Linux namespaces to isolate the program in.
+    pub namespaces: Namespaces,
+    /// Root filesystem to pivot into before dropping privileges (implies a mount namespace).
+    pub rootfs: Option<RootFs>,
+    /// Path to the expected answer. When set, a successful run is checked against it
+    /// and ends with `Success` (accepted) or `WrongAnswer`.
+    pub answer_path: Option<String>,
+    /// How the output is compared with the answer.
+    pub check_mode: CheckMode,
+    /// Special judge run as `checker input output answer` after a successful run,
+    /// instead of comparing with `check_mode`. Its `exe_path`, `args` and limits
+    /// describe the checker, and its `error_path` receives the checker message.
+    pub special_judge: Option<Box<Config>>,
 }
 
+/// UID and GID of the unprivileged `nobody` user, which compilers run as by default.
+pub const NOBODY: u32 = 65534;
+
+/// `PATH` given to compilers by [`Config::compiler`].
+pub const COMPILER_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
 impl Config {
-    pub(crate) fn check(&self) -> bool {
-        !((self.max_cpu_time < 1 && self.max_cpu_time != -1)
-            || (self.max_real_time < 1 && self.max_real_time != -1)
-            || (self.max_stack < 1)
-            || (self.max_memory < 1 && self.max_memory != -1)
-            || (self.max_process_number < 1 && self.max_process_number != -1)
-            || (self.max_output_size < 1 && self.max_output_size != -1))
+    /// Looser defaults for running a compiler: longer time limits, more memory,
+    /// any number of processes, large output files and the [`SeccompRuleName::Compiler`] rule.
+    /// The compiler runs as [`NOBODY`], so its working directory must be writable by that user.
+    /// `env` holds a [`COMPILER_PATH`] so that drivers such as gcc can find the assembler and linker.
+    pub fn compiler() -> Config {
+        Config {
+            max_cpu_time: Some(Duration::from_secs(10)),
+            max_real_time: Some(Duration::from_secs(20)),
+            max_memory: Some(ByteSize::gib(1)),
+            max_stack: Some(ByteSize::mib(64)),
+            max_process_number: None,
+            max_output_size: Some(ByteSize::mib(256)),
+            seccomp_rule_name: Some(SeccompRuleName::Compiler),
+            uid: NOBODY,
+            gid: NOBODY,
+            env: vec![format!("PATH={}", COMPILER_PATH)],
+            ..Default::default()
+        }
+    }
+
+    /// Reads a config from a file. Files ending in `.toml` are parsed as TOML, anything else as JSON.
+    /// # Errors
+    /// Returns a message if the file cannot be read or parsed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config, String> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read config: {}", e))?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&content).map_err(|e| format!("Invalid config: {}", e))
+        } else {
+            serde_json::from_str(&content).map_err(|e| format!("Invalid config: {}", e))
+        }
+    }
+
+    /// Settings of the per-run cgroup, if the run is confined in one.
+    pub(crate) fn cgroup_config(&self) -> Option<CgroupConfig> {
+        match (&self.cgroup, self.memory_limit_mode) {
+            (Some(cgroup), _) => Some(cgroup.clone()),
+            (None, MemoryLimitMode::Cgroup) => Some(CgroupConfig::default()),
+            (None, _) => None,
+        }
+    }
+
+    /// Starts a [`ConfigBuilder`] for running `exe_path`.
+    pub fn builder(exe_path: impl Into<String>) -> ConfigBuilder {
+        ConfigBuilder::new(exe_path)
+    }
+
+    /// Checks the configuration and reports every invalid field, not just the first.
+    /// Limits must not be zero, `exe_path` must be an executable file (unless it
+    /// lies in `rootfs`), an explicit `argv[0]` must not be empty, and `uid` and `gid` must
+    /// not be `u32::MAX`, which `setuid` and `setgid` take as "unchanged".
+    /// Inherited descriptors must be open, captured output needs a `max_output_size`,
+    /// and the input and output must be files for a
+    /// special judge, or a file or captured for `answer_path`.
+    /// A `special_judge` is validated as well.
+    /// # Errors
+    /// Returns the invalid fields with what is wrong with each.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        self.validate_into("", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_into(&self, prefix: &str, errors: &mut Vec<ConfigError>) {
+        let mut invalid = |field: &str, message: String| {
+            errors.push(ConfigError {
+                field: format!("{}{}", prefix, field),
+                message,
+            })
+        };
+        let limits = [
+            (
+                "max_cpu_time",
+                self.max_cpu_time.is_some_and(|limit| limit.is_zero()),
+            ),
+            (
+                "max_real_time",
+                self.max_real_time.is_some_and(|limit| limit.is_zero()),
+            ),
+            ("max_memory", self.max_memory == Some(ByteSize(0))),
+            ("max_stack", self.max_stack == Some(ByteSize(0))),
+            ("max_output_size", self.max_output_size == Some(ByteSize(0))),
+        ];
+        for (field, zero) in limits {
+            if zero {
+                invalid(field, "is zero, use `None` for unlimited".to_string());
+            }
+        }
+
+        if self.exe_path.is_empty() {
+            invalid("exe_path", "is empty".to_string());
+        } else if self.rootfs.is_none() {
+            // Inside a rootfs the path is only resolved after the pivot.
+            match std::fs::metadata(&self.exe_path) {
+                Err(e) => invalid("exe_path", format!("{}: {}", self.exe_path, e)),
+                Ok(meta) if !meta.is_file() => {
+                    invalid("exe_path", format!("{} is not a file", self.exe_path))
+                }
+                Ok(meta) if meta.permissions().mode() & 0o111 == 0 => {
+                    invalid("exe_path", format!("{} is not executable", self.exe_path))
+                }
+                Ok(_) => {}
+            }
+        }
+        // A tmpfs of size 0 is not empty but unlimited.
+        if let Some(rootfs) = &self.rootfs {
+            if rootfs.root_size == ByteSize(0) {
+                invalid("rootfs.root_size", "is zero".to_string());
+            }
+            if rootfs.tmp_size == Some(ByteSize(0)) {
+                invalid(
+                    "rootfs.tmp_size",
+                    "is zero, use `None` for no `/tmp`".to_string(),
+                );
+            }
+        }
+        if self
+            .cgroup
+            .as_ref()
+            .is_some_and(|cgroup| cgroup.cpu_quota == Some(0))
+        {
+            invalid(
+                "cgroup.cpu_quota",
+                "is zero, use `None` for unlimited".to_string(),
+            );
+        }
+        if self.args.first().is_some_and(|arg| arg.is_empty()) {
+            invalid("args", "argv[0] is empty".to_string());
+        }
+        if self.uid == u32::MAX {
+            invalid("uid", format!("{} is reserved", self.uid));
+        }
+        if self.gid == u32::MAX {
+            invalid("gid", format!("{} is reserved", self.gid));
+        }
+        let fds = [
+            ("stdin", self.stdin.as_ref().and_then(InputSource::fd)),
+            ("stdout", self.stdout.as_ref().and_then(OutputSink::fd)),
+            ("stderr", self.stderr.as_ref().and_then(OutputSink::fd)),
+        ];
+        for (field, fd) in fds {
+            if let Some(fd) = fd
+                && (fd < 0 || unsafe { nix::libc::fcntl(fd, nix::libc::F_GETFD) } == -1)
+            {
+                invalid(field, format!("{} is not an open file descriptor", fd));
+            }
+        }
+        if self.max_output_size.is_none() {
+            for (field, sink) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+                if sink == &Some(OutputSink::Capture) {
+                    invalid(field, "is captured without a `max_output_size`".to_string());
+                }
+            }
+        }
+        if self.special_judge.is_some() {
+            if self.input_file().is_none() {
+                invalid("stdin", "must be a file for the special judge".to_string());
+            }
+            if self.output_file().is_none() {
+                invalid("stdout", "must be a file for the special judge".to_string());
+            }
+        } else if self.answer_path.is_some()
+            && self.output_file().is_none()
+            && self.stdout != Some(OutputSink::Capture)
+        {
+            invalid(
+                "stdout",
+                "must be a file or captured to be checked against `answer_path`".to_string(),
+            );
+        }
+
+        if let Some(special_judge) = &self.special_judge {
+            special_judge.validate_into(&format!("{}special_judge.", prefix), errors);
+        }
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
-            max_cpu_time: 1000,
-            max_real_time: 2000,
-            max_memory: 128 * 1024 * 1024,
-            max_stack: 32 * 1024 * 1024,
-            max_process_number: 1,
-            max_output_size: 1000000,
+            max_cpu_time: Some(Duration::from_secs(1)),
+            cpu_time_grace: Duration::from_millis(50),
+            include_descendant_cpu_time: false,
+            max_real_time: Some(Duration::from_secs(2)),
+            max_memory: Some(ByteSize::mib(128)),
+            memory_limit_mode: MemoryLimitMode::default(),
+            max_stack: Some(ByteSize::mib(32)),
+            max_process_number: NonZeroU32::new(1),
+            max_output_size: Some(ByteSize(1000000)),
             exe_path: Default::default(),
             input_path: Default::default(),
             output_path: Default::default(),
             error_path: Default::default(),
+            stdin: None,
+            stdout: None,
+            stderr: None,
             args: Default::default(),
             env: Default::default(),
             log_path: Default::default(),
             seccomp_rule_name: Some(SeccompRuleName::General),
+            seccomp_policy: None,
             uid: 0,
             gid: 0,
+            cgroup: None,
+            namespaces: Namespaces::default(),
+            rootfs: None,
+            answer_path: None,
+            check_mode: CheckMode::default(),
+            special_judge: None,
         }
     }
 }
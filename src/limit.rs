@@ -0,0 +1,273 @@
+use crate::{Config, ConfigError};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+/// A size in bytes, used for memory, stack and output limits.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// `n` bytes.
+    pub const fn b(n: u64) -> ByteSize {
+        ByteSize(n)
+    }
+
+    /// `n` kibibytes.
+    pub const fn kib(n: u64) -> ByteSize {
+        ByteSize(n.saturating_mul(1024))
+    }
+
+    /// `n` mebibytes.
+    pub const fn mib(n: u64) -> ByteSize {
+        ByteSize(n.saturating_mul(1024 * 1024))
+    }
+
+    /// `n` gibibytes.
+    pub const fn gib(n: u64) -> ByteSize {
+        ByteSize(n.saturating_mul(1024 * 1024 * 1024))
+    }
+
+    /// The size in bytes.
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes", self.0)
+    }
+}
+
+/// The limits of a [`Config`] in the integer layout used before typed limits:
+/// times in milliseconds, sizes in bytes, and -1 for unlimited.
+/// Config files and the command line still use this layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegacyLimits {
+    /// Maximum CPU time in milliseconds (-1 for unlimited).
+    pub max_cpu_time: i32,
+    /// CPU time in milliseconds the program may use beyond `max_cpu_time`.
+    pub cpu_time_grace: i32,
+    /// Maximum real time in milliseconds (-1 for unlimited).
+    pub max_real_time: i32,
+    /// Maximum memory in bytes (-1 for unlimited).
+    pub max_memory: i64,
+    /// Maximum stack size in bytes (-1 for unlimited).
+    pub max_stack: i64,
+    /// Maximum number of processes (-1 for unlimited).
+    pub max_process_number: i32,
+    /// Maximum output size in bytes (-1 for unlimited).
+    pub max_output_size: i64,
+}
+
+impl Config {
+    /// The limits in the legacy integer layout. Values too large for it are clamped.
+    pub fn legacy_limits(&self) -> LegacyLimits {
+        let clamp_i32 = |value: i64| value.min(i32::MAX as i64) as i32;
+        LegacyLimits {
+            max_cpu_time: clamp_i32(legacy::millis(self.max_cpu_time)),
+            cpu_time_grace: clamp_i32(legacy::millis(Some(self.cpu_time_grace))),
+            max_real_time: clamp_i32(legacy::millis(self.max_real_time)),
+            max_memory: legacy::bytes(self.max_memory),
+            max_stack: legacy::bytes(self.max_stack),
+            max_process_number: self
+                .max_process_number
+                .map_or(-1, |number| clamp_i32(number.get() as i64)),
+            max_output_size: legacy::bytes(self.max_output_size),
+        }
+    }
+
+    /// Sets the limits from the legacy integer layout.
+    /// # Errors
+    /// Returns the fields that are neither -1 nor a valid limit, leaving the config unchanged.
+    pub fn set_legacy_limits(&mut self, limits: LegacyLimits) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut check = |field: &str, result: Result<(), String>| {
+            if let Err(message) = result {
+                errors.push(ConfigError {
+                    field: field.to_string(),
+                    message,
+                });
+            }
+        };
+        let mut config = self.clone();
+        check(
+            "max_cpu_time",
+            legacy::to_millis(limits.max_cpu_time as i64).map(|limit| config.max_cpu_time = limit),
+        );
+        check(
+            "cpu_time_grace",
+            legacy::to_millis(limits.cpu_time_grace as i64).and_then(|grace| {
+                config.cpu_time_grace = grace.ok_or("-1 is not a grace period")?;
+                Ok(())
+            }),
+        );
+        check(
+            "max_real_time",
+            legacy::to_millis(limits.max_real_time as i64)
+                .map(|limit| config.max_real_time = limit),
+        );
+        check(
+            "max_memory",
+            legacy::to_bytes(limits.max_memory).map(|limit| config.max_memory = limit),
+        );
+        check(
+            "max_stack",
+            legacy::to_bytes(limits.max_stack).map(|limit| config.max_stack = limit),
+        );
+        check(
+            "max_process_number",
+            legacy::to_count(limits.max_process_number as i64)
+                .map(|limit| config.max_process_number = limit),
+        );
+        check(
+            "max_output_size",
+            legacy::to_bytes(limits.max_output_size).map(|limit| config.max_output_size = limit),
+        );
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        *self = config;
+        Ok(())
+    }
+}
+
+/// Conversions between typed limits and the legacy integers, also used by serde.
+pub(crate) mod legacy {
+    use super::*;
+
+    pub(crate) fn millis(limit: Option<Duration>) -> i64 {
+        limit.map_or(-1, |limit| limit.as_millis().min(i64::MAX as u128) as i64)
+    }
+
+    pub(crate) fn bytes(limit: Option<ByteSize>) -> i64 {
+        limit.map_or(-1, |limit| limit.0.min(i64::MAX as u64) as i64)
+    }
+
+    fn unlimited_or<T>(value: i64, limit: impl FnOnce(u64) -> T) -> Result<Option<T>, String> {
+        match value {
+            -1 => Ok(None),
+            value if value < 0 => Err(format!("{} is neither -1 (unlimited) nor a limit", value)),
+            value => Ok(Some(limit(value as u64))),
+        }
+    }
+
+    pub(crate) fn to_millis(value: i64) -> Result<Option<Duration>, String> {
+        unlimited_or(value, Duration::from_millis)
+    }
+
+    pub(crate) fn to_bytes(value: i64) -> Result<Option<ByteSize>, String> {
+        unlimited_or(value, ByteSize)
+    }
+
+    pub(crate) fn to_count(value: i64) -> Result<Option<NonZeroU32>, String> {
+        unlimited_or(value, |value| value)?
+            .map(|value| {
+                u32::try_from(value)
+                    .ok()
+                    .and_then(NonZeroU32::new)
+                    .ok_or_else(|| format!("{} is neither -1 (unlimited) nor a limit", value))
+            })
+            .transpose()
+    }
+
+    /// Serializes an optional time limit as milliseconds, -1 if unlimited.
+    pub(crate) mod millis_or_unlimited {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            limit: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            millis(*limit).serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            to_millis(i64::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// Serializes a time as milliseconds.
+    pub(crate) mod duration_millis {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            time: &Duration,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            (time.as_millis().min(u64::MAX as u128) as u64).serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Duration, D::Error> {
+            u64::deserialize(deserializer).map(Duration::from_millis)
+        }
+    }
+
+    /// Serializes an optional size limit as bytes, -1 if unlimited.
+    pub(crate) mod bytes_or_unlimited {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            limit: &Option<ByteSize>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            bytes(*limit).serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<ByteSize>, D::Error> {
+            to_bytes(i64::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// Serializes an optional number as itself, -1 if unlimited.
+    pub(crate) mod number_or_unlimited {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            limit: &Option<u64>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            limit
+                .map_or(-1, |limit| limit.min(i64::MAX as u64) as i64)
+                .serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<u64>, D::Error> {
+            unlimited_or(i64::deserialize(deserializer)?, |value| value)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// Serializes an optional process limit as a number, -1 if unlimited.
+    pub(crate) mod count_or_unlimited {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            limit: &Option<NonZeroU32>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            limit
+                .map_or(-1, |limit| limit.get() as i64)
+                .serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<NonZeroU32>, D::Error> {
+            to_count(i64::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use nix::libc;
+use nix::mount::{MsFlags, mount};
+use nix::sched::{CloneFlags, setns, unshare};
+use nix::sys::signal::{Signal, kill};
+use nix::sys::wait::waitpid;
+use nix::unistd::{ForkResult, Pid, fork, sethostname};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+
+/// Linux namespaces to isolate the sandboxed program in.
+/// All namespaces are disabled by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Namespaces {
+    /// New mount namespace, so mounts made for the run stay private to it.
+    pub mount: bool,
+    /// New PID namespace, so the program cannot see or signal other processes.
+    /// The program runs below a minimal init, as the kernel would drop the signals with a
+    /// default action, such as SIGXFSZ and SIGPIPE, that are sent to the init of a namespace.
+    /// Implies a mount namespace, in which a fresh `/proc` is mounted.
+    pub pid: bool,
+    /// New IPC namespace (System V IPC, POSIX message queues).
+    pub ipc: bool,
+    /// New UTS namespace with its own hostname.
+    pub uts: bool,
+    /// New, empty network namespace without any connectivity.
+    pub net: bool,
+}
+
+impl Namespaces {
+    /// Enables every supported namespace.
+    pub fn all() -> Namespaces {
+        Namespaces {
+            mount: true,
+            pid: true,
+            ipc: true,
+            uts: true,
+            net: true,
+        }
+    }
+
+    pub(crate) fn clone_flags(&self) -> CloneFlags {
+        let mut flags = CloneFlags::empty();
+        if self.mount || self.pid {
+            flags |= CloneFlags::CLONE_NEWNS;
+        }
+        if self.pid {
+            flags |= CloneFlags::CLONE_NEWPID;
+        }
+        if self.ipc {
+            flags |= CloneFlags::CLONE_NEWIPC;
+        }
+        if self.uts {
+            flags |= CloneFlags::CLONE_NEWUTS;
+        }
+        if self.net {
+            flags |= CloneFlags::CLONE_NEWNET;
+        }
+        flags
+    }
+}
+
+/// The init of a PID namespace created for a run. It reaps the orphans of the program,
+/// and is killed with every process left in the namespace when dropped.
+pub(crate) struct PidNamespace {
+    init: Pid,
+}
+
+impl PidNamespace {
+    /// Clones the init of a new PID namespace.
+    fn create() -> nix::Result<PidNamespace> {
+        let pid = unsafe {
+            libc::syscall(
+                libc::SYS_clone,
+                libc::CLONE_NEWPID as libc::c_long | libc::SIGCHLD as libc::c_long,
+                0,
+                0,
+                0,
+                0,
+            )
+        };
+        match pid {
+            -1 => Err(nix::errno::Errno::last()),
+            0 => run_init(),
+            pid => Ok(PidNamespace {
+                init: Pid::from_raw(pid as libc::pid_t),
+            }),
+        }
+    }
+}
+
+impl Drop for PidNamespace {
+    fn drop(&mut self) {
+        // The init is not reaped before, so its PID cannot have been reused.
+        let _ = kill(self.init, Signal::SIGKILL);
+        let _ = waitpid(self.init, None);
+    }
+}
+
+/// Body of the init of a PID namespace, which reaps its children until it is killed.
+/// Runs in a child of a possibly multithreaded process, so it only makes raw system calls.
+fn run_init() -> ! {
+    unsafe {
+        // Descriptors of the judger, such as the pipes of other runs, must not be kept open.
+        libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0);
+        let mut sigchld: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut sigchld);
+        libc::sigaddset(&mut sigchld, libc::SIGCHLD);
+        libc::sigprocmask(libc::SIG_BLOCK, &sigchld, std::ptr::null_mut());
+        loop {
+            while libc::waitpid(-1, std::ptr::null_mut(), libc::WNOHANG) > 0 {}
+            libc::sigwaitinfo(&sigchld, std::ptr::null_mut());
+        }
+    }
+}
+
+/// Forks the sandboxed child, which unshares its namespaces in [`setup_child`].
+/// A PID namespace only applies to children, so in that case an init is cloned into a new one
+/// first, and the child is forked into it as its second process. The child thus stays a child
+/// of the caller, which can trace and wait for it as usual.
+pub(crate) fn fork_into(
+    namespaces: &Namespaces,
+) -> nix::Result<(ForkResult, Option<PidNamespace>)> {
+    if !namespaces.pid {
+        return unsafe { fork() }.map(|forked| (forked, None));
+    }
+    let io_errno =
+        |e: std::io::Error| nix::errno::Errno::from_raw(e.raw_os_error().unwrap_or(libc::EIO));
+    // Only the namespace of the children of this thread changes, and it is restored below.
+    let own = File::open("/proc/thread-self/ns/pid_for_children").map_err(io_errno)?;
+    let pid_namespace = PidNamespace::create()?;
+    let new = File::open(format!("/proc/{}/ns/pid", pid_namespace.init)).map_err(io_errno)?;
+    setns(&new, CloneFlags::CLONE_NEWPID)?;
+    let forked = unsafe { fork() };
+    if let Ok(ForkResult::Child) = forked {
+        // The init belongs to the caller.
+        std::mem::forget(pid_namespace);
+        return Ok((ForkResult::Child, None));
+    }
+    let restored = setns(&own, CloneFlags::CLONE_NEWPID);
+    let forked = forked?;
+    if let (Err(e), ForkResult::Parent { child }) = (restored, forked) {
+        let _ = kill(child, Signal::SIGKILL);
+        let _ = waitpid(child, None);
+        return Err(e);
+    }
+    Ok((forked, Some(pid_namespace)))
+}
+
+/// Finishes the namespace setup inside the child, before the program is executed.
+pub(crate) fn setup_child(namespaces: &Namespaces) -> nix::Result<()> {
+    let flags = namespaces.clone_flags();
+    // The child was forked into its PID namespace already.
+    let unshared = flags - CloneFlags::CLONE_NEWPID;
+    if !unshared.is_empty() {
+        unshare(unshared)?;
+    }
+    if flags.contains(CloneFlags::CLONE_NEWNS) {
+        // Keep every mount made from here on out of the host's mount table.
+        mount(
+            None::<&str>,
+            "/",
+            None::<&str>,
+            MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+            None::<&str>,
+        )?;
+    }
+    if namespaces.pid {
+        mount(
+            Some("proc"),
+            "/proc",
+            Some("proc"),
+            MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+            None::<&str>,
+        )?;
+    }
+    if namespaces.uts {
+        sethostname("judger")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,404 @@
+use crate::runner::run_privileged;
+use crate::{
+    Aggregation, ByteSize, CheckMode, Config, ErrorCode, JudgerError, LanguageRegistry, NOBODY,
+    RunResult, ScoreReport, Scoring, Subtask, TestCase, compile,
+};
+use nix::unistd::Uid;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::{PermissionsExt, chown};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Names of the manifest file looked up in a problem package, in order.
+const MANIFEST_NAMES: [&str; 2] = ["problem.toml", "problem.json"];
+
+/// Distinguishes the work directories of concurrent judgings in one process.
+static WORK_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Manifest of a problem package, read from `problem.toml` or `problem.json`.
+/// Paths are relative to the package directory.
+/// # Example
+/// ```toml
+/// time_limit = 1000
+/// memory_limit = 268435456
+/// tests = "tests/*.in"
+/// checker = "checker.cpp"
+///
+/// [languages.python]
+/// time_limit = 3000
+///
+/// [scoring]
+/// subtasks = [{ name = "all", points = 100, tests = [0, 1] }]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Problem {
+    /// CPU time limit in milliseconds. The real time limit is twice as long.
+    pub time_limit: u64,
+    /// Memory limit in bytes.
+    pub memory_limit: ByteSize,
+    /// Limits replacing `time_limit` and `memory_limit` for some languages.
+    #[serde(default)]
+    pub languages: HashMap<String, LimitOverride>,
+    /// Pattern of the input files. `*` and `?` are allowed in the file name.
+    /// Tests are ordered by name, shorter names first, so `2.in` precedes `10.in`.
+    pub tests: String,
+    /// Extension replacing the input extension to find the answer file.
+    #[serde(default = "default_answer_extension")]
+    pub answer_extension: String,
+    /// How the output is compared with the answer when there is no checker.
+    #[serde(default)]
+    pub check_mode: CheckMode,
+    /// Source of a testlib checker (C++). `testlib.h` is looked up in the package.
+    pub checker: Option<String>,
+    /// Source of a testlib interactor (C++).
+    pub interactor: Option<String>,
+    /// Subtasks. Without them, every test is worth the same share of 100 points.
+    pub scoring: Option<Scoring>,
+}
+
+/// Per-language limits of a [`Problem`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitOverride {
+    /// CPU time limit in milliseconds.
+    pub time_limit: Option<u64>,
+    /// Memory limit in bytes.
+    pub memory_limit: Option<ByteSize>,
+}
+
+/// Outcome of judging a submission against a problem package.
+#[derive(Debug, Serialize)]
+pub struct JudgeReport {
+    /// Result of compiling the submission.
+    pub compile: RunResult,
+    /// Result of every test, `None` for tests that were skipped.
+    pub tests: Vec<Option<RunResult>>,
+    /// Score of the submission, if it compiled.
+    pub score: Option<ScoreReport>,
+}
+
+fn default_answer_extension() -> String {
+    "ans".to_string()
+}
+
+impl Problem {
+    /// Reads the manifest of the problem package in `dir`.
+    /// # Errors
+    /// Returns a message if no manifest exists or it cannot be parsed.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Problem, String> {
+        let dir = dir.as_ref();
+        let path = MANIFEST_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .ok_or_else(|| format!("No problem manifest in {}", dir.display()))?;
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read problem manifest: {}", e))?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&content).map_err(|e| format!("Invalid problem manifest: {}", e))
+        } else {
+            serde_json::from_str(&content).map_err(|e| format!("Invalid problem manifest: {}", e))
+        }
+    }
+
+    /// Lists the input and answer files of the package in `dir`, in test order.
+    /// Outputs are placed in `work_dir`.
+    /// # Errors
+    /// Returns a message if the test directory cannot be read.
+    pub fn test_cases<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        work_dir: &Path,
+    ) -> Result<Vec<TestCase>, String> {
+        let pattern = dir.as_ref().join(&self.tests);
+        let test_dir = pattern.parent().unwrap_or(Path::new("."));
+        let file_pattern = pattern
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut inputs: Vec<PathBuf> = fs::read_dir(test_dir)
+            .map_err(|e| format!("Failed to read {}: {}", test_dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name().is_some_and(|name| {
+                    wildcard_match(file_pattern.as_bytes(), name.to_string_lossy().as_bytes())
+                })
+            })
+            .collect();
+        inputs.sort_by_key(|path| (path.as_os_str().len(), path.clone()));
+        Ok(inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| TestCase {
+                input_path: input.to_string_lossy().into_owned(),
+                output_path: work_dir
+                    .join(format!("{}.out", index))
+                    .to_string_lossy()
+                    .into_owned(),
+                answer_path: Some(
+                    input
+                        .with_extension(&self.answer_extension)
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
+            })
+            .collect())
+    }
+
+    /// Limits for `language`, with the per-language overrides applied.
+    fn limits(&self, language: &str) -> (Duration, ByteSize) {
+        let limits = self.languages.get(language).cloned().unwrap_or_default();
+        (
+            Duration::from_millis(limits.time_limit.unwrap_or(self.time_limit)),
+            limits.memory_limit.unwrap_or(self.memory_limit),
+        )
+    }
+}
+
+/// Compiles `source` in `language`, then runs and checks it against every test of the
+/// problem package in `dir`. The checker and interactor of the package are compiled first.
+/// Intermediate files are kept in a temporary directory that is removed afterwards.
+/// Every program runs as `nobody`: the source is copied to the temporary directory, while the
+/// package must be readable by `nobody` for the checker and interactor to be compiled and run.
+/// # Arguments
+/// * `dir` - The problem package directory
+/// * `languages` - The language profiles, also used to compile the checker and interactor as `cpp`
+/// * `language` - The name of the language of the submission
+/// * `source` - Path to the source file of the submission
+/// # Returns
+/// * `Result<JudgeReport, JudgerError>` - The report. `Err` means the package or a judge program
+///   is broken ([`JudgerError::InvalidProblem`]) or the sandbox failed.
+pub fn judge<P: AsRef<Path>>(
+    dir: P,
+    languages: &LanguageRegistry,
+    language: &str,
+    source: &str,
+) -> Result<JudgeReport, JudgerError> {
+    let dir = dir.as_ref();
+    let problem = Problem::from_dir(dir).map_err(JudgerError::InvalidProblem)?;
+    let work_dir = std::env::temp_dir().join(format!(
+        "judger-{}-{}",
+        std::process::id(),
+        WORK_DIR_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(&work_dir).map_err(|source| JudgerError::Io {
+        stage: ErrorCode::SystemError,
+        source,
+    })?;
+    let report = judge_in(dir, &problem, languages, language, source, &work_dir);
+    let _ = fs::remove_dir_all(&work_dir);
+    report
+}
+
+fn judge_in(
+    dir: &Path,
+    problem: &Problem,
+    languages: &LanguageRegistry,
+    language: &str,
+    source: &str,
+    work_dir: &Path,
+) -> Result<JudgeReport, JudgerError> {
+    let profile = languages
+        .get(language)
+        .ok_or_else(|| JudgerError::InvalidConfig(format!("Unknown language: {}", language)))?;
+    let path = |name: &str| work_dir.join(name).to_string_lossy().into_owned();
+    let log_path = path("judger.log");
+    let exe = path("main");
+    if !Uid::current().is_root() {
+        return Err(JudgerError::RootRequired);
+    }
+    // Compilers write into the work directory as `nobody` until every program is built.
+    set_mode(work_dir, 0o777)?;
+    let copy = work_dir.join(Path::new(source).file_name().unwrap_or_default());
+    fs::copy(source, &copy).map_err(system_error)?;
+    set_mode(&copy, 0o644)?;
+    let source = copy.to_string_lossy().into_owned();
+
+    let compile_result = match profile.compile_config(&source, &exe) {
+        Some(config) => build(config, &path("compile.err"), &log_path)?,
+        None => RunResult::default(),
+    };
+    if compile_result.result != ErrorCode::Success {
+        return Ok(JudgeReport {
+            compile: compile_result,
+            tests: vec![],
+            score: None,
+        });
+    }
+
+    let checker = match &problem.checker {
+        Some(checker) => Some(Box::new(judge_program(
+            dir,
+            languages,
+            checker,
+            &path("checker"),
+            &path("checker.err"),
+            &log_path,
+        )?)),
+        None => None,
+    };
+    let interactor = match &problem.interactor {
+        Some(interactor) => Some(judge_program(
+            dir,
+            languages,
+            interactor,
+            &path("interactor"),
+            &path("interactor.err"),
+            &log_path,
+        )?),
+        None => None,
+    };
+    // The programs are handed to root so that the submission cannot replace them.
+    for program in ["main", "checker", "interactor"] {
+        let program = work_dir.join(program);
+        if program.exists() {
+            chown(&program, Some(0), Some(0)).map_err(system_error)?;
+            set_mode(&program, 0o755)?;
+        }
+    }
+    set_mode(work_dir, 0o711)?;
+
+    let cases = problem
+        .test_cases(dir, work_dir)
+        .map_err(JudgerError::InvalidProblem)?;
+    let (time_limit, memory_limit) = problem.limits(language);
+    let config = profile.run_config(
+        &source,
+        &exe,
+        Config {
+            max_cpu_time: Some(time_limit),
+            max_real_time: Some(time_limit.saturating_mul(2)),
+            max_memory: Some(memory_limit),
+            // RLIMIT_NPROC counts every process and thread of `nobody`, including other runs,
+            // so forks are left to the seccomp rule of the language.
+            max_process_number: None,
+            error_path: "/dev/null".to_string(),
+            log_path: log_path.clone(),
+            check_mode: problem.check_mode,
+            special_judge: checker,
+            uid: NOBODY,
+            gid: NOBODY,
+            ..Default::default()
+        },
+    );
+
+    let scoring = problem.scoring.clone().unwrap_or_else(|| Scoring {
+        subtasks: vec![Subtask {
+            name: "all".to_string(),
+            points: 100.0,
+            aggregation: Aggregation::Sum,
+            tests: (0..cases.len()).collect(),
+            weights: vec![],
+            dependencies: vec![],
+        }],
+        skip_on_failure: false,
+    });
+    let mut tests: Vec<Option<RunResult>> = cases.iter().map(|_| None).collect();
+    // Sandbox failures are kept aside, as scoring only passes messages through.
+    let mut sandbox_error = None;
+    let score = scoring.run(|index| {
+        let case = cases
+            .get(index)
+            .ok_or_else(|| format!("Scoring refers to missing test {}", index))?;
+        let case_config = Config {
+            input_path: case.input_path.clone(),
+            output_path: case.output_path.clone(),
+            answer_path: case.answer_path.clone(),
+            ..config.clone()
+        };
+        let result = run_privileged(&case_config, interactor.as_ref()).map_err(|e| {
+            let message = e.to_string();
+            sandbox_error = Some(e);
+            message
+        })?;
+        tests[index] = Some(result.clone());
+        Ok(result)
+    });
+    let score = match (score, sandbox_error) {
+        (_, Some(e)) => return Err(e),
+        (score, None) => score.map_err(JudgerError::InvalidProblem)?,
+    };
+
+    Ok(JudgeReport {
+        compile: compile_result,
+        tests,
+        score: Some(score),
+    })
+}
+
+/// Sets the permission bits of `path`.
+fn set_mode(path: &Path, mode: u32) -> Result<(), JudgerError> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(system_error)
+}
+
+fn system_error(source: std::io::Error) -> JudgerError {
+    JudgerError::Io {
+        stage: ErrorCode::SystemError,
+        source,
+    }
+}
+
+/// Runs a compile config from a language profile, keeping the diagnostics in `error_path`.
+fn build(config: Config, error_path: &str, log_path: &str) -> Result<RunResult, JudgerError> {
+    compile(&Config {
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: error_path.to_string(),
+        log_path: log_path.to_string(),
+        ..config
+    })
+}
+
+/// Compiles a C++ checker or interactor of the package and returns its config.
+fn judge_program(
+    dir: &Path,
+    languages: &LanguageRegistry,
+    source: &str,
+    exe: &str,
+    error_path: &str,
+    log_path: &str,
+) -> Result<Config, JudgerError> {
+    let source = dir.join(source).to_string_lossy().into_owned();
+    let mut config = languages
+        .get("cpp")
+        .and_then(|cpp| cpp.compile_config(&source, exe))
+        .ok_or_else(|| {
+            JudgerError::InvalidConfig("No cpp language profile to compile judge programs".into())
+        })?;
+    config
+        .args
+        .extend(["-I".to_string(), dir.to_string_lossy().into_owned()]);
+    let result = build(config, error_path, log_path)?;
+    if result.result != ErrorCode::Success {
+        return Err(JudgerError::InvalidProblem(format!(
+            "Failed to compile {}: {}",
+            source, result.result
+        )));
+    }
+    Ok(Config {
+        exe_path: exe.to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: error_path.to_string(),
+        log_path: log_path.to_string(),
+        ..Config::compiler()
+    })
+}
+
+/// Matches `name` against a pattern where `*` matches any run of bytes and `?` any single byte.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
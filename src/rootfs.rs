@@ -0,0 +1,157 @@
+use crate::ByteSize;
+use nix::mount::{MntFlags, MsFlags, mount, umount2};
+use nix::unistd::{chdir, pivot_root};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A host path made visible inside the sandbox root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BindMount {
+    /// Path on the host.
+    pub source: String,
+    /// Path inside the sandbox root.
+    pub target: String,
+    /// Whether the program may write to the mount.
+    #[serde(default)]
+    pub writable: bool,
+}
+
+impl BindMount {
+    /// Creates a read-only bind mount of `source` at `target`.
+    pub fn read_only(source: &str, target: &str) -> BindMount {
+        BindMount {
+            source: source.to_string(),
+            target: target.to_string(),
+            writable: false,
+        }
+    }
+}
+
+/// Root filesystem the program is confined to.
+/// The root is a read-only tmpfs assembled from bind mounts, with an optional writable `/tmp`.
+/// It is built inside the run's own mount namespace, so concurrent runs can share `path`.
+/// Every path of the `Config` that is used after `execve`, such as `exe_path`, refers to this root,
+/// while `input_path`, `output_path` and `error_path` are opened on the host beforehand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RootFs {
+    /// Host directory used as the mount point of the new root.
+    pub path: String,
+    /// Bind mounts making host directories or files visible. Missing sources are skipped.
+    pub bind_mounts: Vec<BindMount>,
+    /// Size of the read-only tmpfs the root is built on. It only holds the mount points.
+    pub root_size: ByteSize,
+    /// Size of the writable tmpfs mounted at `/tmp` (`None` for no `/tmp`).
+    /// Serialized in bytes, -1 for no `/tmp`.
+    #[serde(with = "crate::limit::legacy::bytes_or_unlimited")]
+    pub tmp_size: Option<ByteSize>,
+    /// Working directory of the program inside the new root.
+    pub work_dir: String,
+}
+
+impl Default for RootFs {
+    fn default() -> Self {
+        RootFs {
+            path: "/tmp/judger-root".to_string(),
+            bind_mounts: ["/bin", "/lib", "/lib64", "/usr"]
+                .iter()
+                .map(|path| BindMount::read_only(path, path))
+                .collect(),
+            root_size: ByteSize::mib(16),
+            tmp_size: Some(ByteSize::mib(64)),
+            work_dir: "/".to_string(),
+        }
+    }
+}
+
+/// Builds the sandbox root and pivots into it.
+/// Must run inside a private mount namespace, before privileges are dropped.
+pub(crate) fn enter(rootfs: &RootFs, mount_proc: bool) -> nix::Result<()> {
+    let root = Path::new(&rootfs.path);
+    fs::create_dir_all(root).map_err(io_errno)?;
+    mount(
+        Some("tmpfs"),
+        root,
+        Some("tmpfs"),
+        MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+        Some(format!("size={},mode=755", rootfs.root_size.as_u64()).as_str()),
+    )?;
+
+    for bind in &rootfs.bind_mounts {
+        let source = Path::new(&bind.source);
+        if !source.exists() {
+            continue;
+        }
+        let target = root.join(bind.target.trim_start_matches('/'));
+        if source.is_dir() {
+            fs::create_dir_all(&target).map_err(io_errno)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(io_errno)?;
+            }
+            fs::File::create(&target).map_err(io_errno)?;
+        }
+        mount(
+            Some(source),
+            &target,
+            None::<&str>,
+            MsFlags::MS_BIND | MsFlags::MS_REC,
+            None::<&str>,
+        )?;
+        if !bind.writable {
+            // A bind mount only becomes read-only when remounted.
+            mount(
+                None::<&str>,
+                &target,
+                None::<&str>,
+                MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | MsFlags::MS_NOSUID,
+                None::<&str>,
+            )?;
+        }
+    }
+
+    if let Some(tmp_size) = rootfs.tmp_size {
+        let tmp = root.join("tmp");
+        fs::create_dir_all(&tmp).map_err(io_errno)?;
+        mount(
+            Some("tmpfs"),
+            &tmp,
+            Some("tmpfs"),
+            MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+            Some(format!("size={},mode=1777", tmp_size.as_u64()).as_str()),
+        )?;
+    }
+
+    if mount_proc {
+        let proc = root.join("proc");
+        fs::create_dir_all(&proc).map_err(io_errno)?;
+        mount(
+            Some("proc"),
+            &proc,
+            Some("proc"),
+            MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+            None::<&str>,
+        )?;
+    }
+
+    let old_root = root.join(".old_root");
+    fs::create_dir_all(&old_root).map_err(io_errno)?;
+    pivot_root(root, &old_root)?;
+    chdir("/")?;
+    umount2("/.old_root", MntFlags::MNT_DETACH)?;
+    fs::remove_dir("/.old_root").map_err(io_errno)?;
+    mount(
+        None::<&str>,
+        "/",
+        None::<&str>,
+        MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+        None::<&str>,
+    )?;
+    chdir(rootfs.work_dir.as_str())
+}
+
+fn io_errno(e: std::io::Error) -> nix::errno::Errno {
+    nix::errno::Errno::from_raw(e.raw_os_error().unwrap_or(nix::libc::EIO))
+}
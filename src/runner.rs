@@ -1,49 +1,75 @@
-use crate::{Config, ErrorCode, LogLevel, Logger, child_process};
+use crate::CheckMode;
+use crate::cgroup::Cgroup;
+use crate::checker::{check, check_output};
+use crate::namespace::PidNamespace;
+use crate::stdio::{Captured, ParentStdio, Pump};
+use crate::{
+    ByteSize, Config, ErrorCode, JudgerError, LogLevel, Logger, child, limit, namespace, spj, stdio,
+};
 use nix::fcntl::OFlag;
 use nix::libc;
+use nix::sys::ptrace;
 use nix::sys::signal::Signal;
-use nix::unistd::{ForkResult, Uid, fork};
+use nix::unistd::{ForkResult, Pid, Uid};
 use serde::Serialize;
-use std::os::fd::{AsRawFd, FromRawFd};
-use std::path::PathBuf;
-use std::process::Stdio;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Mutex, mpsc};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
 
 /// Result of the program
-#[derive(Debug, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Default)]
 pub struct RunResult {
-    /// CPU time used in milliseconds.
-    pub cpu_time: i32,
-    /// Real time used in milliseconds.
-    pub real_time: i32,
-    /// Memory used in bytes.
-    pub memory: i64,
+    /// CPU time used, the sum of `user_time` and `system_time`. Serialized in milliseconds.
+    #[serde(serialize_with = "limit::legacy::duration_millis::serialize")]
+    pub cpu_time: Duration,
+    /// CPU time spent in user mode. Serialized in milliseconds.
+    #[serde(serialize_with = "limit::legacy::duration_millis::serialize")]
+    pub user_time: Duration,
+    /// CPU time spent in the kernel. Serialized in milliseconds.
+    #[serde(serialize_with = "limit::legacy::duration_millis::serialize")]
+    pub system_time: Duration,
+    /// Real time used. Serialized in milliseconds.
+    #[serde(serialize_with = "limit::legacy::duration_millis::serialize")]
+    pub real_time: Duration,
+    /// Peak memory used.
+    pub memory: ByteSize,
     /// Signal that terminated the process.
     pub signal: i32,
     /// Exit code of the process.
     pub exit_code: i32,
+    /// Name of the syscall blocked by seccomp, if it could be determined.
+    pub syscall: Option<String>,
     /// Error code if any error occurred during execution.
     pub result: ErrorCode,
+    /// Fraction of the points awarded to the output, if it was checked.
+    pub score: Option<f64>,
+    /// Resource usage and outcome of the interactor, if one was used.
+    pub interactor: Option<Box<RunResult>>,
+    /// Standard output, if it was captured, truncated at `max_output_size`.
+    pub stdout: Option<Vec<u8>>,
+    /// Standard error, if it was captured, truncated at `max_output_size`.
+    pub stderr: Option<Vec<u8>>,
 }
 
 /// Runs the judger with the given configuration.
 /// Returns a `RunResult` containing the execution results.
 /// # Arguments
 /// * `config` - A reference to the `Config` struct containing the judger configuration
-/// * `interactor` - An optional `PathBuf` for the interactor program
+/// * `interactor` - An optional `Config` for a testlib interactor, run in the sandbox as
+///   `interactor input output` with its standard input and output connected to the program
 /// # Returns
-/// * `Result<RunResult, String>` - On success, returns `Ok(RunResult)`. On failure, returns `Err(String)` with an error message.
-pub fn run(config: &Config, interactor: Option<PathBuf>) -> Result<RunResult, String> {
-    let mut logger = Logger::new(&config.log_path)
-        .map_err(|e| format!("Failed to open log file {}: {:?}", &config.log_path, e))?;
-    let mut result = RunResult::default();
+/// * `Result<RunResult, JudgerError>` - The verdict of the program is in `RunResult::result`.
+///   `Err` means the sandbox itself failed and says at which stage and why.
+pub fn run(config: &Config, interactor: Option<&Config>) -> Result<RunResult, JudgerError> {
+    let mut logger = Logger::new(&config.log_path).map_err(JudgerError::Log)?;
 
     let uid = Uid::current();
     if !uid.is_root() {
-        result.result = ErrorCode::RootRequired;
         logger
             .write(
                 LogLevel::Fatal,
@@ -51,156 +77,923 @@ pub fn run(config: &Config, interactor: Option<PathBuf>) -> Result<RunResult, St
                 line!(),
                 format_args!("Error: Root privileges are required to run the judger."),
             )
-            .map_err(|e| format!("Failed to write to log file: {:?}", e))?;
+            .map_err(JudgerError::Log)?;
+        return Err(JudgerError::RootRequired);
+    }
+    run_privileged(config, interactor)
+}
+
+/// Runs the program and checks its output, once the caller is known to be root.
+pub(crate) fn run_privileged(
+    config: &Config,
+    interactor: Option<&Config>,
+) -> Result<RunResult, JudgerError> {
+    run_controlled(config, interactor, &RunControl::default())
+}
+
+/// Lets a [`RunHandle`](crate::RunHandle) follow and cancel a run supervised by another thread.
+#[derive(Debug, Default)]
+pub(crate) struct RunControl {
+    cancelled: AtomicBool,
+    /// Receives the PID of the program once it is forked.
+    started: Mutex<Option<mpsc::Sender<Pid>>>,
+}
+
+impl RunControl {
+    /// A control reporting the PID of the program to `started`.
+    pub(crate) fn new(started: mpsc::Sender<Pid>) -> Self {
+        RunControl {
+            cancelled: AtomicBool::new(false),
+            started: Mutex::new(Some(started)),
+        }
+    }
+
+    /// Kills the programs of the run that are still running or started later.
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn report_started(&self, pid: Pid) {
+        if let Ok(mut started) = self.started.lock()
+            && let Some(started) = started.take()
+        {
+            let _ = started.send(pid);
+        }
+    }
+
+    /// Stops reporting the PID, so a run that failed before forking the program disconnects.
+    pub(crate) fn close(&self) {
+        if let Ok(mut started) = self.started.lock() {
+            started.take();
+        }
+    }
+}
+
+/// Runs the program and checks its output, stopping as soon as `control` is cancelled.
+pub(crate) fn run_controlled(
+    config: &Config,
+    interactor: Option<&Config>,
+    control: &RunControl,
+) -> Result<RunResult, JudgerError> {
+    let mut logger = Logger::new(&config.log_path).map_err(JudgerError::Log)?;
+
+    let Some(interactor) = interactor else {
+        let mut result = execute(config, None, control, true)?;
+        if result.result == ErrorCode::Success {
+            if let Some(special_judge) = &config.special_judge {
+                let checker = spj::checker_config(special_judge, config);
+                let checker_result = execute(&checker, None, control, false)?;
+                if checker_result.result == ErrorCode::Cancelled {
+                    result.result = ErrorCode::Cancelled;
+                    return Ok(result);
+                }
+                (result.result, result.score) = spj::checker_verdict(&checker, &checker_result);
+                if result.result == ErrorCode::SpjError {
+                    logger
+                        .write(
+                            LogLevel::Fatal,
+                            file!(),
+                            line!(),
+                            format_args!("Error: Special judge {} failed.", special_judge.exe_path),
+                        )
+                        .map_err(JudgerError::Log)?;
+                }
+            } else if let Some(answer_path) = &config.answer_path {
+                (result.result, result.score) = answer_verdict(
+                    result.stdout.as_deref(),
+                    config.output_file().unwrap_or(&config.output_path),
+                    answer_path,
+                    config.check_mode,
+                )?;
+            }
+        }
         return Ok(result);
+    };
+
+    let (inter_config, user_fds, inter_fds) = interaction(config, interactor)?;
+    // ptrace requests must come from the thread that forked the tracee,
+    // so each program is forked and supervised by its own thread.
+    let (result, inter_result) = thread::scope(|scope| {
+        let inter_thread = scope.spawn(|| execute(&inter_config, Some(inter_fds), control, false));
+        let result = execute(config, Some(user_fds), control, true);
+        let inter_result = inter_thread.join().unwrap_or_else(|_| {
+            Err(JudgerError::Io {
+                stage: ErrorCode::SystemError,
+                source: io::Error::other("interactor thread panicked"),
+            })
+        });
+        (result, inter_result)
+    });
+    interaction_verdict(&mut logger, interactor, result?, inter_result?)
+}
+
+/// Standard input and output of a program and of its interactor, each connected to the other.
+type InteractionFds = (OwnedFd, OwnedFd);
+
+/// Connects the program of `config` with `interactor` through two pipes.
+/// Returns the interactor config, run as `interactor input output`, and the
+/// standard input and output of the program and of the interactor.
+/// The pair shares the `max_real_time` of the program, so an interactor waiting for a
+/// deadlocked program is stopped with it instead of running on without a limit.
+pub(crate) fn interaction(
+    config: &Config,
+    interactor: &Config,
+) -> Result<(Config, InteractionFds, InteractionFds), JudgerError> {
+    let pipe_failed = |source| JudgerError::Sys {
+        stage: ErrorCode::SystemError,
+        source,
+    };
+    let input_path = config.input_file().ok_or_else(|| {
+        JudgerError::InvalidConfig("stdin: the interactor reads the input from a file".to_string())
+    })?;
+    let (user_stdin, inter_stdout) = nix::unistd::pipe2(OFlag::O_CLOEXEC).map_err(pipe_failed)?;
+    let (inter_stdin, user_stdout) = nix::unistd::pipe2(OFlag::O_CLOEXEC).map_err(pipe_failed)?;
+    let mut inter_config = interactor.clone();
+    inter_config.args = spj::testlib_args(
+        interactor,
+        &[
+            input_path,
+            config.output_file().unwrap_or(&config.output_path),
+        ],
+    );
+    if let Some(limit) = config.max_real_time {
+        inter_config.max_real_time = Some(
+            inter_config
+                .max_real_time
+                .map_or(limit, |own| own.min(limit)),
+        );
     }
+    Ok((
+        inter_config,
+        (user_stdin, user_stdout),
+        (inter_stdin, inter_stdout),
+    ))
+}
+
+/// Verdict and score of the output checked against the answer with `mode`.
+/// `captured` is the captured output, which is read from `output_path` if it was not captured.
+pub(crate) fn answer_verdict(
+    captured: Option<&[u8]>,
+    output_path: &str,
+    answer_path: &str,
+    mode: CheckMode,
+) -> Result<(ErrorCode, Option<f64>), JudgerError> {
+    let read_failed = |source| JudgerError::Io {
+        stage: ErrorCode::SystemError,
+        source,
+    };
+    let verdict = match captured {
+        Some(output) => {
+            let answer = std::fs::read(answer_path).map_err(read_failed)?;
+            check_output(output, &answer, mode)
+        }
+        None => check(output_path, answer_path, mode).map_err(read_failed)?,
+    };
+    let score = if verdict == ErrorCode::Success {
+        1.0
+    } else {
+        0.0
+    };
+    Ok((verdict, Some(score)))
+}
 
-    if !config.check() {
-        result.result = ErrorCode::InvalidConfig;
+/// Combines the results of a program and its interactor into the result of the program.
+pub(crate) fn interaction_verdict(
+    logger: &mut Logger,
+    interactor: &Config,
+    mut result: RunResult,
+    inter_result: RunResult,
+) -> Result<RunResult, JudgerError> {
+    if result.result == ErrorCode::Cancelled || inter_result.result == ErrorCode::Cancelled {
+        result.result = ErrorCode::Cancelled;
+        result.score = None;
+        result.interactor = Some(Box::new(inter_result));
+        return Ok(result);
+    }
+    let message = std::fs::read_to_string(&interactor.error_path).unwrap_or_default();
+    let (verdict, score) = spj::testlib_verdict(&inter_result, message.trim());
+    // A program killed by SIGPIPE only saw the other side quit first.
+    let user_broken_pipe = result.signal == Signal::SIGPIPE as i32;
+    let inter_broken_pipe = inter_result.signal == Signal::SIGPIPE as i32;
+    // Both were stopped by the real-time limit of the pair, which the program is judged by.
+    let pair_timed_out = result.result == ErrorCode::RealTimeLimitExceeded
+        && inter_result.result == ErrorCode::RealTimeLimitExceeded;
+    let inter_excused =
+        (inter_broken_pipe || pair_timed_out) && result.result != ErrorCode::Success;
+    if verdict == ErrorCode::SpjError && !inter_excused {
         logger
             .write(
                 LogLevel::Fatal,
                 file!(),
                 line!(),
-                format_args!("Error: Invalid configuration provided."),
+                format_args!(
+                    "Error: Interactor {} failed: {:?}, stderr: {}",
+                    interactor.exe_path, inter_result.result, message
+                ),
             )
-            .map_err(|e| format!("Failed to write to log file: {:?}", e))?;
-        return Ok(result);
+            .map_err(JudgerError::Log)?;
+        result.result = ErrorCode::SpjError;
+        result.score = None;
+    } else if result.result == ErrorCode::Success || user_broken_pipe {
+        result.result = verdict;
+        result.score = score;
     }
+    result.interactor = Some(Box::new(inter_result));
+    Ok(result)
+}
 
-    let start_time = SystemTime::now();
-    let (user_stdin, inter_stdout) = nix::unistd::pipe2(OFlag::O_CLOEXEC)
-        .map_err(|e| format!("Failed to create pipe for interactor: {:?}", e))?;
-    let (inter_stdin, user_stdout) = nix::unistd::pipe2(OFlag::O_CLOEXEC)
-        .map_err(|e| format!("Failed to create pipe for user program: {:?}", e))?;
-    match unsafe { fork() } {
-        Ok(ForkResult::Parent { child }) => {
-            let inter_child = interactor.and_then(|path| {
-                let stdin = unsafe { Stdio::from_raw_fd(inter_stdin.as_raw_fd()) };
-                let stdout = unsafe { Stdio::from_raw_fd(inter_stdout.as_raw_fd()) };
-                std::mem::forget(inter_stdin);
-                std::mem::forget(inter_stdout);
-                std::process::Command::new(path)
-                    .args(vec![&config.input_path, &config.output_path])
-                    .stdin(stdin)
-                    .stdout(stdout)
-                    .stderr(Stdio::piped())
-                    .spawn()
-                    .ok()
-            });
-            let shared_child = Arc::new(Mutex::new(inter_child));
-            let shared_child_clone = shared_child.clone();
-            let cancel_flag = Arc::new(AtomicBool::new(false));
-            if config.max_real_time != -1 {
-                let cancel_flag_clone = Arc::clone(&cancel_flag);
-                let max_real_time = config.max_real_time;
-                thread::spawn(move || {
-                    thread::sleep(Duration::from_millis(max_real_time as u64));
-                    if !cancel_flag_clone.load(Ordering::SeqCst) {
-                        let _ = nix::sys::signal::kill(child, Signal::SIGKILL);
-                        if let Ok(mut guard) = shared_child.lock()
-                            && let Some(inter) = guard.as_mut()
-                        {
-                            let _ = inter.kill();
-                        }
-                    }
-                });
-            }
+/// Runs a single program in the sandbox and determines its verdict from its exit status and usage.
+/// `fds` replaces the standard input and output files of the program with the given pipe ends.
+/// With `report_pid`, the PID of the program is reported to `control` once it is forked.
+fn execute(
+    config: &Config,
+    fds: Option<(OwnedFd, OwnedFd)>,
+    control: &RunControl,
+    report_pid: bool,
+) -> Result<RunResult, JudgerError> {
+    let mut sandbox = Sandbox::spawn(config, fds, true)?;
+    // On failure, the program is killed as the sandbox is dropped.
+    let pump = Pump::start(sandbox.take_stdio(), config.max_output_size, &sandbox.pidfd).map_err(
+        |source| JudgerError::Io {
+            stage: ErrorCode::SystemError,
+            source,
+        },
+    )?;
+    if report_pid {
+        control.report_started(sandbox.child);
+    }
+    let exit = supervise(&mut sandbox, config, control).map_err(|source| JudgerError::Sys {
+        stage: ErrorCode::WaitFailed,
+        source,
+    })?;
+    let captured = pump.map_or((None, None), Pump::finish);
+    sandbox.finish(config, exit, captured)
+}
 
-            let mut status: i32 = 0;
-            let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
-            let wait_pid = unsafe { libc::wait4(child.as_raw(), &mut status, 0, &mut rusage) };
-            if wait_pid == -1 {
-                result.result = ErrorCode::WaitFailed;
-                return Ok(result);
-            }
+/// A program forked into the sandbox. It is killed and reaped when dropped before it exited.
+pub(crate) struct Sandbox {
+    child: Pid,
+    pidfd: OwnedFd,
+    start_time: Instant,
+    reaped: bool,
+    piped: bool,
+    error_reader: Option<OwnedFd>,
+    stdio: ParentStdio,
+    tracees: Tracees,
+    logger: Logger,
+    /// Killed with the processes left in the namespace once the program has been reaped.
+    pid_namespace: Option<PidNamespace>,
+    // Declared last, so the cgroup is only removed once the program is gone.
+    cgroup: Option<Cgroup>,
+}
+
+/// How a supervised program ended.
+pub(crate) struct Exit {
+    status: i32,
+    rusage: libc::rusage,
+    real_time: Duration,
+    /// User and system time, in clock ticks, of the children the program waited for.
+    children_ticks: Option<ChildrenTicks>,
+    /// Name of the syscall blocked by seccomp, if the program was traced.
+    syscall: Option<String>,
+    /// Whether the program was killed because the run was cancelled.
+    cancelled: bool,
+}
 
-            let duration = SystemTime::now()
-                .duration_since(start_time)
-                .map(|d| d.as_millis())
-                .map_err(|e| format!("SystemTime error: {:?}", e))?;
-            result.real_time = duration as i32;
-            cancel_flag.store(true, Ordering::SeqCst);
+impl Sandbox {
+    /// Validates `config`, sets up its cgroup and forks the program.
+    /// With `trace`, the program is traced so that blocked syscalls can be named,
+    /// and it must then be supervised by the calling thread.
+    pub(crate) fn spawn(
+        config: &Config,
+        fds: Option<(OwnedFd, OwnedFd)>,
+        trace: bool,
+    ) -> Result<Sandbox, JudgerError> {
+        let mut logger = Logger::new(&config.log_path).map_err(JudgerError::Log)?;
 
-            if libc::WIFSIGNALED(status) {
-                result.signal = libc::WTERMSIG(status);
+        if let Err(errors) = config.validate() {
+            for error in &errors {
+                logger
+                    .write(
+                        LogLevel::Fatal,
+                        file!(),
+                        line!(),
+                        format_args!("Error: Invalid configuration: {}", error),
+                    )
+                    .map_err(JudgerError::Log)?;
             }
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return Err(JudgerError::InvalidConfig(errors.join("; ")));
+        }
 
-            if result.signal == Signal::SIGUSR1 as i32 {
-                result.result = ErrorCode::SystemError;
-            } else {
-                result.exit_code = libc::WEXITSTATUS(status);
-                result.cpu_time = (rusage.ru_utime.tv_sec as i64 * 1000
-                    + (rusage.ru_utime.tv_usec as i64 / 1000))
-                    as i32;
-                result.memory = (rusage.ru_maxrss as i64) * 1024;
-
-                if result.exit_code != 0 {
-                    result.result = ErrorCode::RuntimeError;
-                }
-                if result.signal == Signal::SIGSEGV as i32 {
-                    if config.max_memory != -1 && result.memory > config.max_memory {
-                        result.result = ErrorCode::MemoryLimitExceeded;
-                    } else {
-                        result.result = ErrorCode::RuntimeError;
-                    }
-                } else {
-                    if result.signal != 0 {
-                        result.result = ErrorCode::RuntimeError;
-                    }
-                    if config.max_memory != -1 && result.memory > config.max_memory {
-                        result.result = ErrorCode::MemoryLimitExceeded;
-                    }
-                    if config.max_real_time != -1 && result.real_time > config.max_real_time {
-                        result.result = ErrorCode::RealTimeLimitExceeded;
-                    }
-                    if config.max_cpu_time != -1 && result.cpu_time > config.max_cpu_time {
-                        result.result = ErrorCode::CpuTimeLimitExceeded;
-                    }
+        let cgroup = match config.cgroup_config() {
+            Some(cgroup_config) => match Cgroup::create(&cgroup_config, config) {
+                Ok(cgroup) => Some(cgroup),
+                Err(e) => {
+                    logger
+                        .write(
+                            LogLevel::Fatal,
+                            file!(),
+                            line!(),
+                            format_args!("Error: Failed to set up cgroup: {:?}", e),
+                        )
+                        .map_err(JudgerError::Log)?;
+                    return Err(JudgerError::Io {
+                        stage: ErrorCode::CgroupFailed,
+                        source: e,
+                    });
                 }
-            }
-            if let Ok(mut guard) = shared_child_clone.lock()
-                && let Some(inter) = guard.as_mut()
-            {
-                let status = inter
-                    .wait()
-                    .map_err(|e| format!("Failed to wait for interactor process: {:?}", e))?;
-                if !status.success()
-                    && let Some(mut stderr) = inter.stderr.take()
-                {
-                    let mut err_output = String::new();
-                    use std::io::Read;
-                    let _ = stderr.read_to_string(&mut err_output);
-                    if !err_output.is_empty() {
+            },
+            None => None,
+        };
+
+        let piped = fds.is_some();
+        let (child_stdio, parent_stdio) =
+            stdio::pipes(config, fds).map_err(|source| JudgerError::Sys {
+                stage: ErrorCode::SystemError,
+                source,
+            })?;
+        // The child reports a failed setup step here. The pipe closes without a report on `execve`.
+        let (error_reader, error_writer) =
+            nix::unistd::pipe2(OFlag::O_CLOEXEC).map_err(|source| JudgerError::Sys {
+                stage: ErrorCode::SystemError,
+                source,
+            })?;
+        let start_time = Instant::now();
+        match namespace::fork_into(&config.namespaces) {
+            Ok((ForkResult::Parent { child }, pid_namespace)) => {
+                // Close the parent's pipe ends so each side sees EOF when the other exits.
+                drop(child_stdio);
+                drop(error_writer);
+                match pidfd_open(child) {
+                    Ok(pidfd) => Ok(Sandbox {
+                        child,
+                        pidfd,
+                        start_time,
+                        reaped: false,
+                        piped,
+                        error_reader: Some(error_reader),
+                        stdio: parent_stdio,
+                        tracees: Tracees::default(),
+                        logger,
+                        pid_namespace,
+                        cgroup,
+                    }),
+                    Err(e) => {
+                        // The child is not reaped yet, so its PID cannot have been reused.
+                        let _ = nix::sys::signal::kill(child, Signal::SIGKILL);
+                        let _ = nix::sys::wait::waitpid(child, None);
                         logger
                             .write(
                                 LogLevel::Fatal,
                                 file!(),
                                 line!(),
-                                format_args!("Interactor stderr: {}", err_output),
+                                format_args!("Error: Failed to open a pidfd: {:?}", e),
                             )
-                            .map_err(|e| format!("Failed to write to log file: {:?}", e))?;
-                        result.result = ErrorCode::WrongAnswer(err_output);
+                            .map_err(JudgerError::Log)?;
+                        Err(JudgerError::Sys {
+                            stage: ErrorCode::WaitFailed,
+                            source: e,
+                        })
+                    }
+                }
+            }
+            Ok((ForkResult::Child, _)) => {
+                drop(error_reader);
+                let error = cgroup
+                    .as_ref()
+                    .map_or(Ok(()), |cgroup| {
+                        cgroup.enter().map_err(|source| JudgerError::Child {
+                            stage: ErrorCode::CgroupFailed,
+                            source,
+                        })
+                    })
+                    .and_then(|_| child::setup_and_exec(config, logger, child_stdio.raw(), trace));
+                match error {
+                    Ok(_) => std::process::exit(0),
+                    Err(e) => {
+                        let _ = File::from(error_writer).write_all(&e.to_report());
+                        std::process::exit(e.code().to_i32());
                     }
                 }
             }
+            Err(source) => Err(JudgerError::Sys {
+                stage: ErrorCode::ForkFailed,
+                source,
+            }),
+        }
+    }
 
-            Ok(result)
+    /// Pidfd of the program, readable once it exits.
+    pub(crate) fn pidfd(&self) -> &OwnedFd {
+        &self.pidfd
+    }
+
+    /// Takes the parent ends of the pipes of the in-memory streams, to be pumped by the caller
+    /// while the program runs.
+    pub(crate) fn take_stdio(&mut self) -> ParentStdio {
+        std::mem::take(&mut self.stdio)
+    }
+
+    /// Real time since the program was forked.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// CPU time used so far by the program in microseconds, with its descendants
+    /// if `include_descendant_cpu_time` is set.
+    pub(crate) fn cpu_usage_usec(&self, config: &Config) -> Option<u64> {
+        cpu_usage_usec(self.child, config, self.cgroup.as_ref())
+    }
+
+    /// Sends `signal` to the program, unless it has been reaped already.
+    pub(crate) fn signal(&self, signal: Signal) {
+        if !self.reaped {
+            pidfd_send_signal(&self.pidfd, signal);
         }
-        Ok(ForkResult::Child) => match child_process(
-            config,
-            logger,
-            interactor.map(|_| (user_stdin.as_raw_fd(), user_stdout.as_raw_fd())),
-        ) {
-            Ok(_) => std::process::exit(0),
-            Err(e) => {
-                eprintln!("Child process failed: {:?}", e);
-                std::process::exit(e.to_i32());
-            }
-        },
-        Err(_) => Ok(RunResult {
-            result: ErrorCode::ForkFailed,
+    }
+
+    /// Determines the result of the reaped program, or returns the error its setup failed with.
+    /// `captured` is its captured standard output and error.
+    pub(crate) fn finish(
+        &mut self,
+        config: &Config,
+        exit: Exit,
+        captured: (Option<Captured>, Option<Captured>),
+    ) -> Result<RunResult, JudgerError> {
+        let Exit {
+            status,
+            rusage,
+            real_time,
+            children_ticks,
+            syscall,
+            cancelled,
+        } = exit;
+        let mut result = RunResult {
+            real_time,
+            syscall,
             ..Default::default()
+        };
+        // Processes the program left behind in its PID namespace are killed with it.
+        drop(self.pid_namespace.take());
+
+        let mut report = Vec::new();
+        if let Some(error_reader) = self.error_reader.take()
+            && File::from(error_reader).read_to_end(&mut report).is_ok()
+            && let Some(e) = JudgerError::from_report(&report)
+        {
+            self.logger
+                .write(
+                    LogLevel::Fatal,
+                    file!(),
+                    line!(),
+                    format_args!("Error: Failed to set up the sandbox: {}", e),
+                )
+                .map_err(JudgerError::Log)?;
+            return Err(e);
+        }
+
+        let (stdout, stderr) = captured;
+        let capture_exceeded = [&stdout, &stderr]
+            .into_iter()
+            .flatten()
+            .any(|captured| captured.exceeded);
+        result.stdout = stdout.map(|captured| captured.bytes);
+        result.stderr = stderr.map(|captured| captured.bytes);
+
+        if libc::WIFSIGNALED(status) {
+            result.signal = libc::WTERMSIG(status);
+        }
+        result.exit_code = libc::WEXITSTATUS(status);
+        // `wait4` counts the program and the descendants it waited for.
+        let mut user_usec = timeval_usec(rusage.ru_utime);
+        let mut system_usec = timeval_usec(rusage.ru_stime);
+        if !config.include_descendant_cpu_time
+            && let Some((user_ticks, system_ticks)) = children_ticks
+        {
+            user_usec = user_usec.saturating_sub(user_ticks * tick_usec());
+            system_usec = system_usec.saturating_sub(system_ticks * tick_usec());
+        }
+        result.memory = ByteSize((rusage.ru_maxrss as u64).saturating_mul(1024));
+        let mut oom_killed = false;
+        if let Some(cgroup) = &self.cgroup {
+            let stats = cgroup.stats();
+            if let Some(memory_peak) = stats.memory_peak {
+                result.memory = memory_peak;
+            }
+            if config.include_descendant_cpu_time {
+                user_usec = stats.cpu_user;
+                system_usec = stats.cpu_system;
+            }
+            oom_killed = stats.oom_kills > 0;
+        }
+        result.user_time = Duration::from_micros(user_usec);
+        result.system_time = Duration::from_micros(system_usec);
+        result.cpu_time = result.user_time + result.system_time;
+        let memory_exceeded =
+            oom_killed || config.max_memory.is_some_and(|limit| result.memory > limit);
+
+        if result.exit_code != 0 {
+            result.result = ErrorCode::RuntimeError;
+        }
+        if result.signal == Signal::SIGSEGV as i32 {
+            if memory_exceeded {
+                result.result = ErrorCode::MemoryLimitExceeded;
+            } else {
+                result.result = ErrorCode::RuntimeError;
+            }
+        } else {
+            if result.signal != 0 {
+                result.result = ErrorCode::RuntimeError;
+            }
+            if result.signal == Signal::SIGXFSZ as i32
+                || capture_exceeded
+                || output_limit_exceeded(config, self.piped)
+            {
+                result.result = ErrorCode::OutputLimitExceeded;
+            }
+            if memory_exceeded {
+                result.result = ErrorCode::MemoryLimitExceeded;
+            }
+            if config
+                .max_real_time
+                .is_some_and(|limit| result.real_time > limit)
+            {
+                result.result = ErrorCode::RealTimeLimitExceeded;
+            }
+            if config
+                .max_cpu_time
+                .is_some_and(|limit| result.cpu_time > limit)
+            {
+                result.result = ErrorCode::CpuTimeLimitExceeded;
+            }
+            if result.signal == Signal::SIGSYS as i32 || result.syscall.is_some() {
+                result.result = ErrorCode::SyscallViolation;
+            }
+        }
+        if cancelled {
+            result.result = ErrorCode::Cancelled;
+        }
+
+        Ok(result)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if !self.reaped {
+            pidfd_send_signal(&self.pidfd, Signal::SIGKILL);
+            let _ = nix::sys::wait::waitpid(self.child, None);
+        }
+        self.tracees.kill_descendants();
+    }
+}
+
+/// Checks whether the output or error file grew beyond `max_output_size`, for those that go to
+/// files. The output is not checked when it is `piped` to an interactor.
+/// Only regular files outside `/dev` are considered, so paths such as `/dev/stdout` are ignored.
+fn output_limit_exceeded(config: &Config, piped: bool) -> bool {
+    let Some(max_output_size) = config.max_output_size else {
+        return false;
+    };
+    let output_path = if piped { None } else { config.output_file() };
+    [output_path, config.error_file()]
+        .into_iter()
+        .flatten()
+        .filter(|path| !path.starts_with("/dev/"))
+        .any(|path| {
+            std::fs::metadata(path)
+                .is_ok_and(|meta| meta.is_file() && meta.len() > max_output_size.0)
+        })
+}
+
+fn timeval_usec(time: libc::timeval) -> u64 {
+    (time.tv_sec as u64) * 1_000_000 + time.tv_usec as u64
+}
+
+/// Length of a clock tick of `/proc/<pid>/stat` in microseconds.
+fn tick_usec() -> u64 {
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    1_000_000 / ticks_per_second.max(1) as u64
+}
+
+/// Reads the user and system time of a process and of its waited-for children,
+/// in clock ticks, from `/proc/<pid>/stat`.
+fn proc_cpu_ticks(pid: Pid) -> Option<[u64; 4]> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, the fields after it do not.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+    let mut ticks = [0; 4];
+    for tick in &mut ticks {
+        *tick = fields.next()?.parse().ok()?;
+    }
+    Some(ticks)
+}
+
+/// User and system time, in clock ticks, of the children a process waited for.
+type ChildrenTicks = (u64, u64);
+
+/// How often [`supervise`] checks the limits and the ptrace stops of the child.
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Opens a pidfd referring to `pid`, so that it can be signalled without PID-reuse races.
+fn pidfd_open(pid: Pid) -> nix::Result<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid.as_raw(), 0) };
+    if fd == -1 {
+        return Err(nix::Error::last());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
+
+/// Sends `signal` to the process referred to by `pidfd`.
+pub(crate) fn pidfd_send_signal(pidfd: &OwnedFd, signal: Signal) {
+    unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd.as_raw_fd(),
+            signal as i32,
+            std::ptr::null::<libc::siginfo_t>(),
+            0,
+        );
+    }
+}
+
+/// Waits for the program to terminate, handling its ptrace stops and killing it once it
+/// exceeds `max_real_time` or `max_cpu_time` plus `cpu_time_grace`.
+/// `RLIMIT_CPU` only has a resolution of whole seconds and remains as a backstop.
+/// The program is also killed once `control` is cancelled.
+fn supervise(sandbox: &mut Sandbox, config: &Config, control: &RunControl) -> nix::Result<Exit> {
+    let cpu_time_limit = cpu_time_limit(config);
+    let mut killed = false;
+    let mut cancelled = false;
+    loop {
+        match try_reap(sandbox)? {
+            Some(Reaped::Exited(mut exit)) => {
+                // Descendants left behind would stay stopped under this thread forever.
+                sandbox.tracees.kill_descendants();
+                exit.syscall = sandbox.tracees.syscall.take();
+                exit.cancelled = cancelled;
+                return Ok(*exit);
+            }
+            Some(Reaped::Stopped(status)) => {
+                if sandbox.tracees.handle_stop(sandbox.child, status) {
+                    killed = true;
+                }
+                continue;
+            }
+            None => {}
+        }
+        if sandbox.tracees.poll_descendants() {
+            sandbox.signal(Signal::SIGKILL);
+            killed = true;
+        }
+
+        if !killed && control.is_cancelled() {
+            sandbox.signal(Signal::SIGKILL);
+            (killed, cancelled) = (true, true);
+        }
+        if !killed && limit_exceeded(sandbox, config, cpu_time_limit) {
+            sandbox.signal(Signal::SIGKILL);
+            killed = true;
+        }
+        // The pidfd becomes readable when the child exits, ptrace stops are caught by the timeout.
+        let mut poll_fd = libc::pollfd {
+            fd: sandbox.pidfd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut poll_fd, 1, SUPERVISE_INTERVAL.as_millis() as i32) };
+    }
+}
+
+/// `max_cpu_time` plus `cpu_time_grace` in microseconds, the CPU time at which the program is killed.
+pub(crate) fn cpu_time_limit(config: &Config) -> Option<u64> {
+    config
+        .max_cpu_time
+        .map(|limit| limit.saturating_add(config.cpu_time_grace).as_micros() as u64)
+}
+
+/// Whether the running program exceeded `max_real_time` or `cpu_time_limit`.
+fn limit_exceeded(sandbox: &Sandbox, config: &Config, cpu_time_limit: Option<u64>) -> bool {
+    let real_time_exceeded = config
+        .max_real_time
+        .is_some_and(|limit| sandbox.elapsed() > limit);
+    real_time_exceeded
+        || cpu_time_limit.is_some_and(|limit| {
+            sandbox
+                .cpu_usage_usec(config)
+                .is_some_and(|usage| usage > limit)
+        })
+}
+
+/// A state change of the program collected by [`try_reap`].
+pub(crate) enum Reaped {
+    /// The program terminated and has been reaped.
+    Exited(Box<Exit>),
+    /// The program stopped, by ptrace or job control, with this wait status.
+    Stopped(i32),
+}
+
+/// Collects a pending state change of the program without blocking.
+pub(crate) fn try_reap(sandbox: &mut Sandbox) -> nix::Result<Option<Reaped>> {
+    // Peek at the event first, so an exited child can still be read from /proc.
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let peeked = unsafe {
+        libc::waitid(
+            libc::P_PIDFD,
+            sandbox.pidfd.as_raw_fd() as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WSTOPPED | libc::WNOWAIT | libc::WNOHANG,
+        )
+    };
+    if peeked == -1 {
+        return Err(nix::Error::last());
+    }
+    if unsafe { info.si_pid() } == 0 {
+        return Ok(None);
+    }
+    let children_ticks = matches!(
+        info.si_code,
+        libc::CLD_EXITED | libc::CLD_KILLED | libc::CLD_DUMPED
+    )
+    .then(|| proc_cpu_ticks(sandbox.child).map(|[_, _, user, system]| (user, system)))
+    .flatten();
+    let mut status: i32 = 0;
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    // `WUNTRACED` also collects job-control stops of an untraced program, which would block otherwise.
+    let wait_pid = unsafe {
+        libc::wait4(
+            sandbox.child.as_raw(),
+            &mut status,
+            libc::WUNTRACED,
+            &mut rusage,
+        )
+    };
+    if wait_pid == -1 {
+        return Err(nix::Error::last());
+    }
+    if libc::WIFSTOPPED(status) {
+        return Ok(Some(Reaped::Stopped(status)));
+    }
+    sandbox.reaped = true;
+    Ok(Some(Reaped::Exited(Box::new(Exit {
+        status,
+        rusage,
+        real_time: sandbox.elapsed(),
+        children_ticks,
+        syscall: None,
+        cancelled: false,
+    }))))
+}
+
+/// CPU time used so far by the child in microseconds, with its descendants
+/// if `include_descendant_cpu_time` is set.
+fn cpu_usage_usec(child: Pid, config: &Config, cgroup: Option<&Cgroup>) -> Option<u64> {
+    match cgroup {
+        Some(cgroup) if config.include_descendant_cpu_time => cgroup.cpu_usage(),
+        _ => proc_cpu_ticks(child).map(|[user, system, children_user, children_system]| {
+            let mut ticks = user + system;
+            if config.include_descendant_cpu_time {
+                ticks += children_user + children_system;
+            }
+            ticks * tick_usec()
         }),
     }
 }
+
+/// `si_code` of a SIGSYS raised by a seccomp `SCMP_ACT_TRAP` rule.
+const SYS_SECCOMP: i32 = 1;
+
+/// Layout of the `_sigsys` member of `siginfo_t`, which `libc` does not expose.
+#[repr(C)]
+struct SigsysInfo {
+    si_signo: i32,
+    si_errno: i32,
+    si_code: i32,
+    call_addr: *mut libc::c_void,
+    syscall: i32,
+    arch: u32,
+}
+
+/// Options of a traced program. Its descendants are traced as well, so that none of them
+/// can catch the SIGSYS of a blocked syscall, and they are killed when the tracer exits.
+const TRACE_OPTIONS: ptrace::Options = ptrace::Options::PTRACE_O_EXITKILL
+    .union(ptrace::Options::PTRACE_O_TRACEFORK)
+    .union(ptrace::Options::PTRACE_O_TRACEVFORK)
+    .union(ptrace::Options::PTRACE_O_TRACECLONE)
+    .union(ptrace::Options::PTRACE_O_TRACEEXEC);
+
+/// Tracing state of a traced program and of the processes and threads it created.
+#[derive(Default)]
+struct Tracees {
+    /// Whether the program stopped after its `execve` and the options have been set.
+    exec_stopped: bool,
+    /// Traced descendants, with whether their initial SIGSTOP is still to come.
+    descendants: HashMap<Pid, bool>,
+    /// Name of the syscall blocked by seccomp.
+    syscall: Option<String>,
+}
+
+impl Tracees {
+    /// Handles a ptrace stop of `pid`, the program or a traced descendant, with the wait `status`.
+    /// The first stop of the program comes from `execve` and is only used to set the options.
+    /// Creating a process or thread adds it to the descendants, and its initial SIGSTOP is
+    /// suppressed. A SIGSYS raised by seccomp records the blocked syscall and kills `pid`,
+    /// any other signal is passed on unchanged.
+    /// Returns whether `pid` made a blocked syscall, in which case the program must be killed.
+    fn handle_stop(&mut self, pid: Pid, status: i32) -> bool {
+        let signal = libc::WSTOPSIG(status);
+        if !self.exec_stopped {
+            self.exec_stopped = true;
+            let _ = ptrace::setoptions(pid, TRACE_OPTIONS);
+            if signal == libc::SIGTRAP {
+                let _ = ptrace::cont(pid, None);
+                return false;
+            }
+        }
+        let event = status >> 16;
+        if event != 0 {
+            if matches!(
+                event,
+                libc::PTRACE_EVENT_FORK | libc::PTRACE_EVENT_VFORK | libc::PTRACE_EVENT_CLONE
+            ) && let Ok(descendant) = ptrace::getevent(pid)
+            {
+                self.descendants
+                    .entry(Pid::from_raw(descendant as i32))
+                    .or_insert(true);
+            }
+            let _ = ptrace::cont(pid, None);
+            return false;
+        }
+        if signal == libc::SIGSTOP
+            && let Some(initial) = self.descendants.get_mut(&pid)
+            && *initial
+        {
+            *initial = false;
+            let _ = ptrace::cont(pid, None);
+            return false;
+        }
+        match ptrace::getsiginfo(pid) {
+            Ok(info) => {
+                let sigsys = unsafe { &*(&info as *const libc::siginfo_t as *const SigsysInfo) };
+                if signal == libc::SIGSYS && sigsys.si_code == SYS_SECCOMP {
+                    self.syscall = Some(crate::seccomp::syscall_name(sigsys.syscall));
+                    let _ = nix::sys::signal::kill(pid, Signal::SIGKILL);
+                    return true;
+                }
+                let _ = ptrace::cont(pid, Signal::try_from(signal).ok());
+            }
+            // Group-stop: there is no signal to deliver, just resume the tracee.
+            Err(_) => {
+                let _ = ptrace::cont(pid, None);
+            }
+        }
+        false
+    }
+
+    /// Handles the pending stops of the traced descendants without blocking and forgets
+    /// those that terminated. Their exit must be collected before their parent can wait
+    /// for them, and before the program itself is reported as exited.
+    /// Returns whether one of them made a blocked syscall.
+    fn poll_descendants(&mut self) -> bool {
+        let mut violated = false;
+        let pids: Vec<Pid> = self.descendants.keys().copied().collect();
+        for pid in pids {
+            loop {
+                let mut status: i32 = 0;
+                let waited = unsafe {
+                    libc::waitpid(pid.as_raw(), &mut status, libc::WNOHANG | libc::__WALL)
+                };
+                if waited == 0 {
+                    break;
+                }
+                if waited == -1 || !libc::WIFSTOPPED(status) {
+                    self.descendants.remove(&pid);
+                    break;
+                }
+                violated |= self.handle_stop(pid, status);
+            }
+        }
+        violated
+    }
+
+    /// Kills the traced descendants that are left and collects their exit.
+    /// Their PIDs cannot have been reused, as they are not reaped before the tracer collected them.
+    fn kill_descendants(&mut self) {
+        for (pid, _) in self.descendants.drain() {
+            let _ = nix::sys::signal::kill(pid, Signal::SIGKILL);
+            loop {
+                let mut status: i32 = 0;
+                let waited = unsafe { libc::waitpid(pid.as_raw(), &mut status, libc::__WALL) };
+                if waited == -1 || !libc::WIFSTOPPED(status) {
+                    break;
+                }
+            }
+        }
+    }
+}
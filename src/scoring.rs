@@ -0,0 +1,272 @@
+use crate::{ErrorCode, RunResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// IOI-style scoring: subtasks are groups of tests with a point value.
+/// # Example
+/// ```toml
+/// skip_on_failure = true
+///
+/// [[subtasks]]
+/// name = "small"
+/// points = 30
+/// tests = [0, 1]
+///
+/// [[subtasks]]
+/// name = "large"
+/// points = 70
+/// aggregation = "sum"
+/// tests = [0, 1, 2, 3]
+/// weights = [1, 1, 2, 2]
+/// dependencies = ["small"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scoring {
+    /// Subtasks, in order. A subtask may only depend on earlier subtasks.
+    pub subtasks: Vec<Subtask>,
+    /// Stop running the tests of a `min` subtask after its first failure,
+    /// since its score can no longer change.
+    #[serde(default)]
+    pub skip_on_failure: bool,
+}
+
+/// A group of tests with a point value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Subtask {
+    /// Name of the subtask, referenced by `dependencies`.
+    pub name: String,
+    /// Points awarded for passing every test.
+    pub points: f64,
+    /// How the test scores are combined.
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    /// Indices of the tests in the subtask.
+    pub tests: Vec<usize>,
+    /// Weight of each test for `sum` aggregation (empty for equal weights).
+    #[serde(default)]
+    pub weights: Vec<f64>,
+    /// Subtasks that must receive full points for this one to be scored.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+/// How the scores of the tests in a subtask are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    /// The subtask is worth its points times the lowest test score.
+    #[default]
+    Min,
+    /// The subtask is worth its points times the weighted average test score.
+    Sum,
+}
+
+/// Outcome of a subtask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtaskStatus {
+    /// Every test passed.
+    Passed,
+    /// Some points were awarded.
+    Partial,
+    /// No points were awarded.
+    Failed,
+    /// A dependency did not receive full points, so no test was run.
+    Skipped,
+}
+
+/// Score of a single test within a subtask.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestReport {
+    /// Index of the test.
+    pub index: usize,
+    /// Verdict of the test, `None` if it was skipped.
+    pub result: Option<ErrorCode>,
+    /// Fraction of the points awarded to the test.
+    pub score: f64,
+}
+
+/// Score of a subtask.
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtaskReport {
+    /// Name of the subtask.
+    pub name: String,
+    /// Points awarded.
+    pub score: f64,
+    /// Points available.
+    pub max_score: f64,
+    /// Outcome of the subtask.
+    pub status: SubtaskStatus,
+    /// Scores of the tests, in the order of the subtask.
+    pub tests: Vec<TestReport>,
+}
+
+/// Score of a whole submission.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreReport {
+    /// Points awarded.
+    pub score: f64,
+    /// Points available.
+    pub max_score: f64,
+    /// Scores of the subtasks, in order.
+    pub subtasks: Vec<SubtaskReport>,
+}
+
+impl Scoring {
+    /// Checks that weights match the tests and that dependencies refer to earlier subtasks.
+    /// # Errors
+    /// Returns a message describing the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        for (position, subtask) in self.subtasks.iter().enumerate() {
+            if !subtask.weights.is_empty() && subtask.weights.len() != subtask.tests.len() {
+                return Err(format!(
+                    "Subtask {} has {} tests but {} weights",
+                    subtask.name,
+                    subtask.tests.len(),
+                    subtask.weights.len()
+                ));
+            }
+            for dependency in &subtask.dependencies {
+                if !self.subtasks[..position]
+                    .iter()
+                    .any(|earlier| &earlier.name == dependency)
+                {
+                    return Err(format!(
+                        "Subtask {} depends on {}, which is not an earlier subtask",
+                        subtask.name, dependency
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Scores the results of tests that have already been run.
+    /// # Arguments
+    /// * `results` - The result of every test, indexed like `Subtask::tests`
+    /// # Errors
+    /// Returns a message if the scoring is invalid or a test has no result.
+    pub fn score(&self, results: &[RunResult]) -> Result<ScoreReport, String> {
+        self.evaluate(|index| {
+            results
+                .get(index)
+                .map(|result| (result.result.clone(), test_score(result)))
+                .ok_or_else(|| format!("No result for test {}", index))
+        })
+    }
+
+    /// Scores a submission, running each test on demand through `run_test`.
+    /// Tests shared by several subtasks run once, and tests of skipped subtasks do not run.
+    /// # Arguments
+    /// * `run_test` - Runs the test with the given index
+    /// # Errors
+    /// Returns a message if the scoring is invalid or `run_test` fails.
+    pub fn run<F>(&self, mut run_test: F) -> Result<ScoreReport, String>
+    where
+        F: FnMut(usize) -> Result<RunResult, String>,
+    {
+        self.evaluate(|index| {
+            run_test(index).map(|result| {
+                let score = test_score(&result);
+                (result.result, score)
+            })
+        })
+    }
+
+    fn evaluate<F>(&self, mut run_test: F) -> Result<ScoreReport, String>
+    where
+        F: FnMut(usize) -> Result<(ErrorCode, f64), String>,
+    {
+        self.validate()?;
+        let mut cache: HashMap<usize, (ErrorCode, f64)> = HashMap::new();
+        let mut reports: Vec<SubtaskReport> = Vec::with_capacity(self.subtasks.len());
+        for subtask in &self.subtasks {
+            let blocked = subtask.dependencies.iter().any(|dependency| {
+                reports
+                    .iter()
+                    .any(|report| &report.name == dependency && report.score < report.max_score)
+            });
+            let mut tests = Vec::with_capacity(subtask.tests.len());
+            let mut failed = false;
+            for &index in &subtask.tests {
+                let skip = blocked
+                    || (failed && self.skip_on_failure && subtask.aggregation == Aggregation::Min);
+                if skip {
+                    tests.push(TestReport {
+                        index,
+                        result: None,
+                        score: 0.0,
+                    });
+                    continue;
+                }
+                let (result, score) = match cache.get(&index) {
+                    Some(cached) => cached.clone(),
+                    None => {
+                        let outcome = run_test(index)?;
+                        cache.insert(index, outcome.clone());
+                        outcome
+                    }
+                };
+                failed |= score <= 0.0;
+                tests.push(TestReport {
+                    index,
+                    result: Some(result),
+                    score,
+                });
+            }
+
+            let fraction = match subtask.aggregation {
+                Aggregation::Min => tests.iter().map(|test| test.score).fold(1.0, f64::min),
+                Aggregation::Sum => {
+                    let weight =
+                        |position: usize| subtask.weights.get(position).copied().unwrap_or(1.0);
+                    let total: f64 = (0..tests.len()).map(weight).sum();
+                    if total > 0.0 {
+                        tests
+                            .iter()
+                            .enumerate()
+                            .map(|(position, test)| weight(position) * test.score)
+                            .sum::<f64>()
+                            / total
+                    } else {
+                        1.0
+                    }
+                }
+            };
+            let score = subtask.points * fraction;
+            let status = if blocked {
+                SubtaskStatus::Skipped
+            } else if score >= subtask.points {
+                SubtaskStatus::Passed
+            } else if score > 0.0 {
+                SubtaskStatus::Partial
+            } else {
+                SubtaskStatus::Failed
+            };
+            reports.push(SubtaskReport {
+                name: subtask.name.clone(),
+                score: if blocked { 0.0 } else { score },
+                max_score: subtask.points,
+                status,
+                tests,
+            });
+        }
+        Ok(ScoreReport {
+            score: reports.iter().map(|report| report.score).sum(),
+            max_score: self.subtasks.iter().map(|subtask| subtask.points).sum(),
+            subtasks: reports,
+        })
+    }
+}
+
+/// Fraction of the points of a single test: the checker score if there is one,
+/// otherwise all points for `Success` and none for any other verdict.
+fn test_score(result: &RunResult) -> f64 {
+    match result.score {
+        Some(score) => score.clamp(0.0, 1.0),
+        None if result.result == ErrorCode::Success => 1.0,
+        None => 0.0,
+    }
+}
@@ -1,13 +1,17 @@
 use clap::ValueEnum;
+use libseccomp::error::SeccompError;
 use libseccomp::{ScmpAction, ScmpArgCompare, ScmpCompareOp, ScmpFilterContext, ScmpSyscall};
 use nix::libc;
+use serde::{Deserialize, Serialize};
 
 /// Seccomp rule names for different programming languages and general use.
-#[derive(ValueEnum, Clone, Debug)]
+#[derive(ValueEnum, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum SeccompRuleName {
     /// C/C++ seccomp rules.
     CCpp,
     /// C/C++ seccomp rules with file I/O allowed.
+    #[serde(rename = "c-cpp-file-io")]
     CCppFileIO,
     /// Golang seccomp rules.
     Golang,
@@ -19,21 +23,33 @@ pub enum SeccompRuleName {
     Java,
     /// General seccomp rules.
     General,
+    /// Rules for compilers, which fork and write files but must not use the network
+    /// or interfere with other processes.
+    Compiler,
 }
 
-pub fn load_seccomp_rules(rule_name: &SeccompRuleName) -> Result<(), ()> {
+/// Loads the seccomp rules of `rule_name` into the calling process.
+/// Denied syscalls raise SIGSYS for the tracer to report if `traced`, and kill the
+/// process otherwise, as an untraced program could catch the signal itself.
+pub fn load_seccomp_rules(rule_name: &SeccompRuleName, traced: bool) -> Result<(), SeccompError> {
+    let deny = if traced {
+        ScmpAction::Trap
+    } else {
+        ScmpAction::KillProcess
+    };
     match rule_name {
-        SeccompRuleName::CCpp => c_cpp_seccomp_rules(false),
-        SeccompRuleName::CCppFileIO => c_cpp_seccomp_rules(true),
-        SeccompRuleName::Golang => golang_seccomp_rules(),
-        SeccompRuleName::Node => node_seccomp_rules(),
-        SeccompRuleName::Python => python_seccomp_rules(),
-        SeccompRuleName::Java => java_seccomp_rules(),
-        SeccompRuleName::General => general_seccomp_rules(),
+        SeccompRuleName::CCpp => c_cpp_seccomp_rules(false, deny),
+        SeccompRuleName::CCppFileIO => c_cpp_seccomp_rules(true, deny),
+        SeccompRuleName::Golang => golang_seccomp_rules(deny),
+        SeccompRuleName::Node => node_seccomp_rules(deny),
+        SeccompRuleName::Python => python_seccomp_rules(deny),
+        SeccompRuleName::Java => java_seccomp_rules(deny),
+        SeccompRuleName::General => general_seccomp_rules(deny),
+        SeccompRuleName::Compiler => compiler_seccomp_rules(deny),
     }
 }
 
-fn c_cpp_seccomp_rules(allow_write_file: bool) -> Result<(), ()> {
+fn c_cpp_seccomp_rules(allow_write_file: bool, deny: ScmpAction) -> Result<(), SeccompError> {
     let syscalls_whitelist = [
         "access",
         "arch_prctl",
@@ -63,20 +79,18 @@ fn c_cpp_seccomp_rules(allow_write_file: bool) -> Result<(), ()> {
         "execve",
     ];
 
-    let mut filter = ScmpFilterContext::new(ScmpAction::KillProcess).map_err(|_| ())?;
+    let mut filter = ScmpFilterContext::new(deny)?;
 
     apply_seccomp_filter(&mut filter, &syscalls_whitelist, ScmpAction::Allow)?;
 
     if allow_write_file {
         for name in ["open", "openat", "dup", "dup2", "dup3"].iter() {
-            let syscall = ScmpSyscall::from_name(name).map_err(|_| ())?;
-            filter
-                .add_rule(ScmpAction::Allow, syscall)
-                .map_err(|_| ())?;
+            let syscall = ScmpSyscall::from_name(name)?;
+            filter.add_rule(ScmpAction::Allow, syscall)?;
         }
     } else {
         // 不允许写文件，只允许 read-only 打开
-        let open_sys = ScmpSyscall::from_name("open").map_err(|_| ())?;
+        let open_sys = ScmpSyscall::from_name("open")?;
         // 对参数 1（flags），执行 MaskedEq 比较：
         //   (flags & (O_WRONLY | O_RDWR)) == 0
         let cmp_open = ScmpArgCompare::new(
@@ -84,12 +98,10 @@ fn c_cpp_seccomp_rules(allow_write_file: bool) -> Result<(), ()> {
             ScmpCompareOp::MaskedEqual((libc::O_WRONLY | libc::O_RDWR) as u64),
             0,
         );
-        filter
-            .add_rule_conditional(ScmpAction::Allow, open_sys, &[cmp_open])
-            .map_err(|_| ())?;
+        filter.add_rule_conditional(ScmpAction::Allow, open_sys, &[cmp_open])?;
 
         // openat 系统调用
-        let openat_sys = ScmpSyscall::from_name("openat").map_err(|_| ())?;
+        let openat_sys = ScmpSyscall::from_name("openat")?;
         // 对参数 2（flags），执行 MaskedEq 比较：
         //   (flags & (O_WRONLY | O_RDWR)) == 0
         let cmp_openat = ScmpArgCompare::new(
@@ -97,146 +109,148 @@ fn c_cpp_seccomp_rules(allow_write_file: bool) -> Result<(), ()> {
             ScmpCompareOp::MaskedEqual((libc::O_WRONLY | libc::O_RDWR) as u64),
             0,
         );
-        filter
-            .add_rule_conditional(ScmpAction::Allow, openat_sys, &[cmp_openat])
-            .map_err(|_| ())?;
+        filter.add_rule_conditional(ScmpAction::Allow, openat_sys, &[cmp_openat])?;
     }
 
-    filter.load().map_err(|_| ())?;
+    filter.load()?;
     Ok(())
 }
 
-fn golang_seccomp_rules() -> Result<(), ()> {
+fn golang_seccomp_rules(deny: ScmpAction) -> Result<(), SeccompError> {
     let syscalls_blacklist = ["socket", "fork", "vfork", "kill", "execveat"];
 
-    let mut filter = ScmpFilterContext::new(ScmpAction::Allow).map_err(|_| ())?;
+    let mut filter = ScmpFilterContext::new(ScmpAction::Allow)?;
 
-    apply_seccomp_filter(&mut filter, &syscalls_blacklist, ScmpAction::KillProcess)?;
+    apply_seccomp_filter(&mut filter, &syscalls_blacklist, deny)?;
 
-    filter.load().map_err(|_| ())?;
+    filter.load()?;
     Ok(())
 }
 
-fn node_seccomp_rules() -> Result<(), ()> {
+fn node_seccomp_rules(deny: ScmpAction) -> Result<(), SeccompError> {
     let syscalls_blacklist = ["socket", "fork", "vfork", "kill", "execveat"];
 
-    let mut filter = ScmpFilterContext::new(ScmpAction::Allow).map_err(|_| ())?;
+    let mut filter = ScmpFilterContext::new(ScmpAction::Allow)?;
 
-    apply_seccomp_filter(&mut filter, &syscalls_blacklist, ScmpAction::KillProcess)?;
+    apply_seccomp_filter(&mut filter, &syscalls_blacklist, deny)?;
 
-    filter.load().map_err(|_| ())?;
+    filter.load()?;
     Ok(())
 }
 
-fn python_seccomp_rules() -> Result<(), ()> {
+fn python_seccomp_rules(deny: ScmpAction) -> Result<(), SeccompError> {
     let syscalls_blacklist = ["clone", "fork", "vfork", "kill", "execveat"];
 
-    let mut filter = ScmpFilterContext::new(ScmpAction::Allow).map_err(|_| ())?;
+    let mut filter = ScmpFilterContext::new(ScmpAction::Allow)?;
 
-    apply_seccomp_filter(&mut filter, &syscalls_blacklist, ScmpAction::KillProcess)?;
+    apply_seccomp_filter(&mut filter, &syscalls_blacklist, deny)?;
 
-    // 不允许通过 open/openat 以写方式打开（kill when flags indicate write）
-    let open_sys = ScmpSyscall::from_name("open").map_err(|_| ())?;
+    // 不允许通过 open/openat 以写方式打开（trap when flags indicate write）
+    let open_sys = ScmpSyscall::from_name("open")?;
     let cmp_open_w = ScmpArgCompare::new(
         1,
         ScmpCompareOp::MaskedEqual(libc::O_WRONLY as u64),
         libc::O_WRONLY as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, open_sys, &[cmp_open_w])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, open_sys, &[cmp_open_w])?;
     let cmp_open_rw = ScmpArgCompare::new(
         1,
         ScmpCompareOp::MaskedEqual(libc::O_RDWR as u64),
         libc::O_RDWR as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, open_sys, &[cmp_open_rw])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, open_sys, &[cmp_open_rw])?;
 
-    let openat_sys = ScmpSyscall::from_name("openat").map_err(|_| ())?;
+    let openat_sys = ScmpSyscall::from_name("openat")?;
     let cmp_openat_w = ScmpArgCompare::new(
         2,
         ScmpCompareOp::MaskedEqual(libc::O_WRONLY as u64),
         libc::O_WRONLY as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, openat_sys, &[cmp_openat_w])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, openat_sys, &[cmp_openat_w])?;
     let cmp_openat_rw = ScmpArgCompare::new(
         2,
         ScmpCompareOp::MaskedEqual(libc::O_RDWR as u64),
         libc::O_RDWR as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, openat_sys, &[cmp_openat_rw])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, openat_sys, &[cmp_openat_rw])?;
 
-    filter.load().map_err(|_| ())?;
+    filter.load()?;
     Ok(())
 }
 
-fn java_seccomp_rules() -> Result<(), ()> {
+fn java_seccomp_rules(deny: ScmpAction) -> Result<(), SeccompError> {
     let syscalls_blacklist = ["fork", "vfork", "execveat"];
 
-    let mut filter = ScmpFilterContext::new(ScmpAction::Allow).map_err(|_| ())?;
+    let mut filter = ScmpFilterContext::new(ScmpAction::Allow)?;
 
-    apply_seccomp_filter(&mut filter, &syscalls_blacklist, ScmpAction::KillProcess)?;
-    filter.load().map_err(|_| ())?;
+    apply_seccomp_filter(&mut filter, &syscalls_blacklist, deny)?;
+    filter.load()?;
     Ok(())
 }
 
-fn general_seccomp_rules() -> Result<(), ()> {
+fn general_seccomp_rules(deny: ScmpAction) -> Result<(), SeccompError> {
     let syscalls_blacklist = ["clone", "fork", "vfork", "kill", "execveat"];
 
-    let mut filter = ScmpFilterContext::new(ScmpAction::Allow).map_err(|_| ())?;
+    let mut filter = ScmpFilterContext::new(ScmpAction::Allow)?;
 
-    apply_seccomp_filter(&mut filter, &syscalls_blacklist, ScmpAction::KillProcess)?;
+    apply_seccomp_filter(&mut filter, &syscalls_blacklist, deny)?;
 
-    // 对 socket 使用 KillProcess（与 C 实现保持一致的严格策略）
-    let socket_sys = ScmpSyscall::from_name("socket").map_err(|_| ())?;
-    filter
-        .add_rule(ScmpAction::KillProcess, socket_sys)
-        .map_err(|_| ())?;
+    // 对 socket 使用 deny（与 C 实现保持一致的严格策略）
+    let socket_sys = ScmpSyscall::from_name("socket")?;
+    filter.add_rule(deny, socket_sys)?;
 
-    // 不允许通过 open/openat 以写方式打开（kill when flags indicate write）
-    let open_sys = ScmpSyscall::from_name("open").map_err(|_| ())?;
+    // 不允许通过 open/openat 以写方式打开（trap when flags indicate write）
+    let open_sys = ScmpSyscall::from_name("open")?;
     let cmp_open_w = ScmpArgCompare::new(
         1,
         ScmpCompareOp::MaskedEqual(libc::O_WRONLY as u64),
         libc::O_WRONLY as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, open_sys, &[cmp_open_w])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, open_sys, &[cmp_open_w])?;
     let cmp_open_rw = ScmpArgCompare::new(
         1,
         ScmpCompareOp::MaskedEqual(libc::O_RDWR as u64),
         libc::O_RDWR as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, open_sys, &[cmp_open_rw])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, open_sys, &[cmp_open_rw])?;
 
-    let openat_sys = ScmpSyscall::from_name("openat").map_err(|_| ())?;
+    let openat_sys = ScmpSyscall::from_name("openat")?;
     let cmp_openat_w = ScmpArgCompare::new(
         2,
         ScmpCompareOp::MaskedEqual(libc::O_WRONLY as u64),
         libc::O_WRONLY as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, openat_sys, &[cmp_openat_w])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, openat_sys, &[cmp_openat_w])?;
     let cmp_openat_rw = ScmpArgCompare::new(
         2,
         ScmpCompareOp::MaskedEqual(libc::O_RDWR as u64),
         libc::O_RDWR as u64,
     );
-    filter
-        .add_rule_conditional(ScmpAction::KillProcess, openat_sys, &[cmp_openat_rw])
-        .map_err(|_| ())?;
+    filter.add_rule_conditional(deny, openat_sys, &[cmp_openat_rw])?;
 
-    filter.load().map_err(|_| ())?;
+    filter.load()?;
+    Ok(())
+}
+
+fn compiler_seccomp_rules(deny: ScmpAction) -> Result<(), SeccompError> {
+    let syscalls_blacklist = [
+        "socket",
+        "ptrace",
+        "kill",
+        "tkill",
+        "tgkill",
+        "rt_sigqueueinfo",
+        "rt_tgsigqueueinfo",
+        "pidfd_open",
+        "pidfd_send_signal",
+        "process_vm_readv",
+        "process_vm_writev",
+    ];
+
+    let mut filter = ScmpFilterContext::new(ScmpAction::Allow)?;
+
+    apply_seccomp_filter(&mut filter, &syscalls_blacklist, deny)?;
+    filter.load()?;
     Ok(())
 }
 
@@ -244,10 +258,18 @@ fn apply_seccomp_filter(
     filter: &mut ScmpFilterContext,
     sys_calls: &[&str],
     action: ScmpAction,
-) -> Result<(), ()> {
+) -> Result<(), SeccompError> {
     for syscall_name in sys_calls.iter() {
-        let syscall = ScmpSyscall::from_name(syscall_name).map_err(|_| ())?;
-        filter.add_rule(action, syscall).map_err(|_| ())?;
+        let syscall = ScmpSyscall::from_name(syscall_name)?;
+        filter.add_rule(action, syscall)?;
     }
     Ok(())
 }
+
+/// Resolves a syscall number of the native architecture to its name,
+/// falling back to the number itself when libseccomp does not know it.
+pub(crate) fn syscall_name(number: i32) -> String {
+    ScmpSyscall::from_raw_syscall(number)
+        .get_name()
+        .unwrap_or_else(|_| number.to_string())
+}
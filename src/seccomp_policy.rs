@@ -0,0 +1,211 @@
+use libseccomp::{ScmpAction, ScmpArgCompare, ScmpCompareOp, ScmpFilterContext, ScmpSyscall};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A declarative seccomp policy, loaded from JSON or TOML.
+/// # Example
+/// ```toml
+/// default_action = "trap"
+/// allow = ["read", "write", "exit_group", "execve", "brk", "mmap"]
+///
+/// [[rules]]
+/// syscall = "openat"
+/// action = "allow"
+/// args = [{ index = 2, op = { masked_eq = 3 }, value = 0 }]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeccompPolicy {
+    /// Action for syscalls that no rule matches.
+    pub default_action: PolicyAction,
+    /// Syscalls that are always allowed.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Syscalls that are always denied with `deny_action`.
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Action for the syscalls in `deny`.
+    #[serde(default = "default_deny_action")]
+    pub deny_action: PolicyAction,
+    /// Rules with their own action and optional argument comparisons.
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// Action taken when a policy rule matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Allow the syscall.
+    Allow,
+    /// Kill the process immediately. The verdict cannot name the syscall.
+    Kill,
+    /// Raise SIGSYS, reported as a syscall violation naming the syscall.
+    /// Kills the process like `Kill` when the run is not traced, as the program
+    /// could otherwise catch the signal itself.
+    Trap,
+    /// Allow the syscall and log it to the kernel audit log.
+    Log,
+    /// Fail the syscall with the given errno.
+    Errno(i32),
+}
+
+/// A syscall rule of a [`SeccompPolicy`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Name of the syscall.
+    pub syscall: String,
+    /// Action taken when every argument condition holds.
+    pub action: PolicyAction,
+    /// Argument conditions, all of which must hold.
+    #[serde(default)]
+    pub args: Vec<ArgCondition>,
+}
+
+/// A comparison of a syscall argument against a value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArgCondition {
+    /// Index of the argument, starting at 0.
+    pub index: u32,
+    /// Comparison operator.
+    pub op: CompareOp,
+    /// Value the argument is compared with.
+    pub value: u64,
+}
+
+/// Comparison operators for [`ArgCondition`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOp {
+    /// `arg == value`
+    Eq,
+    /// `arg != value`
+    Ne,
+    /// `arg < value`
+    Lt,
+    /// `arg <= value`
+    Le,
+    /// `arg > value`
+    Gt,
+    /// `arg >= value`
+    Ge,
+    /// `(arg & mask) == value`
+    MaskedEq(u64),
+}
+
+fn default_deny_action() -> PolicyAction {
+    PolicyAction::Trap
+}
+
+impl SeccompPolicy {
+    /// Reads a policy from a file. Files ending in `.toml` are parsed as TOML, anything else as JSON.
+    /// # Errors
+    /// Returns a message if the file cannot be read or parsed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<SeccompPolicy, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read seccomp policy: {}", e))?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&content).map_err(|e| format!("Invalid seccomp policy: {}", e))
+        } else {
+            serde_json::from_str(&content).map_err(|e| format!("Invalid seccomp policy: {}", e))
+        }
+    }
+
+    /// Checks that the policy compiles, e.g. that every syscall name is known to libseccomp.
+    /// # Errors
+    /// Returns a message describing the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        self.build(true).map(|_| ())
+    }
+
+    /// Compiles the policy and loads it into the calling process.
+    /// Unless `traced`, `Trap` actions kill the process instead.
+    pub(crate) fn load(&self, traced: bool) -> Result<(), String> {
+        self.build(traced)?
+            .load()
+            .map_err(|e| format!("Failed to load seccomp policy: {}", e))
+    }
+
+    fn build(&self, traced: bool) -> Result<ScmpFilterContext, String> {
+        let default_action = scmp_action(self.default_action, traced);
+        let mut filter = ScmpFilterContext::new(default_action)
+            .map_err(|e| format!("Invalid default action: {}", e))?;
+        let rules = self
+            .allow
+            .iter()
+            .map(|name| (name, PolicyAction::Allow, &[][..]))
+            .chain(
+                self.deny
+                    .iter()
+                    .map(|name| (name, self.deny_action, &[][..])),
+            )
+            .chain(
+                self.rules
+                    .iter()
+                    .map(|rule| (&rule.syscall, rule.action, &rule.args[..])),
+            );
+        for (name, action, args) in rules {
+            // libseccomp rejects rules that repeat the default action, and they change nothing.
+            let action = scmp_action(action, traced);
+            if action != default_action {
+                add_rule(&mut filter, name, action, args)?;
+            }
+        }
+        Ok(filter)
+    }
+}
+
+fn add_rule(
+    filter: &mut ScmpFilterContext,
+    name: &str,
+    action: ScmpAction,
+    args: &[ArgCondition],
+) -> Result<(), String> {
+    let syscall = ScmpSyscall::from_name(name).map_err(|_| format!("Unknown syscall: {}", name))?;
+    let comparators: Vec<ScmpArgCompare> = args
+        .iter()
+        .map(|arg| ScmpArgCompare::new(arg.index, arg.op.into(), arg.value))
+        .collect();
+    filter
+        .add_rule_conditional(action, syscall, &comparators)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid rule for {}: {}", name, e))
+}
+
+/// Converts a policy action, killing the process instead of trapping unless `traced`.
+/// Only a tracer sees the SIGSYS of `Trap` before a handler the program installed does.
+fn scmp_action(action: PolicyAction, traced: bool) -> ScmpAction {
+    match action {
+        PolicyAction::Trap if !traced => ScmpAction::KillProcess,
+        action => action.into(),
+    }
+}
+
+impl From<PolicyAction> for ScmpAction {
+    fn from(action: PolicyAction) -> Self {
+        match action {
+            PolicyAction::Allow => ScmpAction::Allow,
+            PolicyAction::Kill => ScmpAction::KillProcess,
+            PolicyAction::Trap => ScmpAction::Trap,
+            PolicyAction::Log => ScmpAction::Log,
+            PolicyAction::Errno(errno) => ScmpAction::Errno(errno),
+        }
+    }
+}
+
+impl From<CompareOp> for ScmpCompareOp {
+    fn from(op: CompareOp) -> Self {
+        match op {
+            CompareOp::Eq => ScmpCompareOp::Equal,
+            CompareOp::Ne => ScmpCompareOp::NotEqual,
+            CompareOp::Lt => ScmpCompareOp::Less,
+            CompareOp::Le => ScmpCompareOp::LessOrEqual,
+            CompareOp::Gt => ScmpCompareOp::Greater,
+            CompareOp::Ge => ScmpCompareOp::GreaterEqual,
+            CompareOp::MaskedEq(mask) => ScmpCompareOp::MaskedEqual(mask),
+        }
+    }
+}
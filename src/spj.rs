@@ -0,0 +1,71 @@
+use crate::{Config, ErrorCode, RunResult};
+
+/// testlib exit code of an accepted output.
+const TESTLIB_OK: i32 = 0;
+/// testlib exit code of a wrong answer.
+const TESTLIB_WA: i32 = 1;
+/// testlib exit code of a presentation error.
+const TESTLIB_PE: i32 = 2;
+/// testlib exit code of an output awarded partial points.
+const TESTLIB_POINTS: i32 = 7;
+
+/// Config running a testlib-style special judge as `checker input output answer` in the sandbox.
+/// `special_judge` provides the checker's executable and limits. Its `args` (or just its
+/// `exe_path` if empty) are followed by the input, output and answer paths of `config`.
+pub(crate) fn checker_config(special_judge: &Config, config: &Config) -> Config {
+    let mut checker = special_judge.clone();
+    checker.args = testlib_args(
+        special_judge,
+        &[
+            config.input_file().unwrap_or(&config.input_path),
+            config.output_file().unwrap_or(&config.output_path),
+            config.answer_path.as_deref().unwrap_or("/dev/null"),
+        ],
+    );
+    checker
+}
+
+/// Verdict and score of a finished checker run, with the message it wrote to its `error_path`.
+pub(crate) fn checker_verdict(checker: &Config, result: &RunResult) -> (ErrorCode, Option<f64>) {
+    let message = std::fs::read_to_string(&checker.error_path).unwrap_or_default();
+    testlib_verdict(result, message.trim())
+}
+
+/// Builds the argv of a testlib program: its own arguments followed by `files`.
+pub(crate) fn testlib_args(program: &Config, files: &[&str]) -> Vec<String> {
+    let mut args = if program.args.is_empty() {
+        vec![program.exe_path.clone()]
+    } else {
+        program.args.clone()
+    };
+    args.extend(files.iter().map(|file| file.to_string()));
+    args
+}
+
+/// Maps the outcome of a testlib checker or interactor to a verdict and a score.
+/// Crashes, limit violations and unknown exit codes of the judge program are `SpjError`.
+pub(crate) fn testlib_verdict(result: &RunResult, message: &str) -> (ErrorCode, Option<f64>) {
+    let exited =
+        result.signal == 0 && matches!(result.result, ErrorCode::Success | ErrorCode::RuntimeError);
+    if !exited {
+        return (ErrorCode::SpjError, None);
+    }
+    match result.exit_code {
+        TESTLIB_OK => (ErrorCode::Success, Some(1.0)),
+        TESTLIB_WA => (ErrorCode::WrongAnswer(message.to_string()), Some(0.0)),
+        TESTLIB_PE => (ErrorCode::PresentationError(message.to_string()), Some(0.0)),
+        TESTLIB_POINTS => {
+            // testlib reports partial points as "points <score> <message>".
+            let points = message.strip_prefix("points").unwrap_or(message).trim();
+            match points
+                .split_whitespace()
+                .next()
+                .and_then(|score| score.parse::<f64>().ok())
+            {
+                Some(score) => (ErrorCode::PartiallyCorrect(points.to_string()), Some(score)),
+                None => (ErrorCode::SpjError, None),
+            }
+        }
+        _ => (ErrorCode::SpjError, None),
+    }
+}
@@ -0,0 +1,375 @@
+use crate::runner::pidfd_send_signal;
+use crate::{ByteSize, Config};
+use nix::fcntl::{FcntlArg, OFlag, fcntl};
+use nix::libc;
+use nix::sys::signal::{SigSet, Signal};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Where the standard input of the program comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputSource {
+    /// The file at this path.
+    Path(String),
+    /// This buffer, written to a pipe by the judger while the program runs.
+    Bytes(Vec<u8>),
+    /// An open file descriptor of the judger, inherited by the program.
+    Fd(RawFd),
+    /// `/dev/null`.
+    Null,
+}
+
+/// Where the standard output or error of the program goes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputSink {
+    /// The file at this path, created or truncated.
+    Path(String),
+    /// A pipe drained by the judger into `RunResult::stdout` or `RunResult::stderr`.
+    /// Requires a `max_output_size`. The program is killed once it writes more.
+    Capture,
+    /// An open file descriptor of the judger, inherited by the program.
+    Fd(RawFd),
+    /// `/dev/null`.
+    Null,
+}
+
+impl InputSource {
+    /// The inherited descriptor, if any.
+    pub(crate) fn fd(&self) -> Option<RawFd> {
+        match self {
+            InputSource::Fd(fd) => Some(*fd),
+            _ => None,
+        }
+    }
+}
+
+impl OutputSink {
+    /// The inherited descriptor, if any.
+    pub(crate) fn fd(&self) -> Option<RawFd> {
+        match self {
+            OutputSink::Fd(fd) => Some(*fd),
+            _ => None,
+        }
+    }
+}
+
+impl Config {
+    /// Path of the file the program reads, if its input comes from a file.
+    pub(crate) fn input_file(&self) -> Option<&str> {
+        match &self.stdin {
+            None => Some(&self.input_path),
+            Some(InputSource::Path(path)) => Some(path),
+            Some(InputSource::Null) => Some("/dev/null"),
+            Some(InputSource::Bytes(_) | InputSource::Fd(_)) => None,
+        }
+    }
+
+    /// Path of the file the program writes its output to, if it goes to a file.
+    pub(crate) fn output_file(&self) -> Option<&str> {
+        match &self.stdout {
+            None => Some(&self.output_path),
+            Some(OutputSink::Path(path)) => Some(path),
+            Some(OutputSink::Null) => Some("/dev/null"),
+            Some(OutputSink::Capture | OutputSink::Fd(_)) => None,
+        }
+    }
+
+    /// Path of the file the program writes its standard error to, if it goes to a file.
+    pub(crate) fn error_file(&self) -> Option<&str> {
+        match &self.stderr {
+            None => Some(&self.error_path),
+            Some(OutputSink::Path(path)) => Some(path),
+            Some(OutputSink::Null) => Some("/dev/null"),
+            Some(OutputSink::Capture | OutputSink::Fd(_)) => None,
+        }
+    }
+}
+
+/// Pipe ends set up by the parent that replace standard streams of the program.
+#[derive(Debug, Default)]
+pub(crate) struct ChildStdio {
+    pub(crate) stdin: Option<OwnedFd>,
+    pub(crate) stdout: Option<OwnedFd>,
+    pub(crate) stderr: Option<OwnedFd>,
+}
+
+impl ChildStdio {
+    /// The descriptors replacing standard input, output and error, in this order.
+    pub(crate) fn raw(&self) -> [Option<RawFd>; 3] {
+        [&self.stdin, &self.stdout, &self.stderr].map(|fd| fd.as_ref().map(AsRawFd::as_raw_fd))
+    }
+}
+
+/// Parent ends of the pipes of in-memory standard streams.
+#[derive(Debug, Default)]
+pub(crate) struct ParentStdio {
+    /// Write end of the standard input, with the bytes to feed it.
+    pub(crate) stdin: Option<(OwnedFd, Vec<u8>)>,
+    pub(crate) stdout: Option<OwnedFd>,
+    pub(crate) stderr: Option<OwnedFd>,
+}
+
+/// Creates the pipes for the in-memory streams of `config`. `piped` are the standard input
+/// and output connected to an interactor, which take precedence over `stdin` and `stdout`.
+pub(crate) fn pipes(
+    config: &Config,
+    piped: Option<(OwnedFd, OwnedFd)>,
+) -> nix::Result<(ChildStdio, ParentStdio)> {
+    let mut child = ChildStdio::default();
+    let mut parent = ParentStdio::default();
+    match piped {
+        Some((input, output)) => {
+            child.stdin = Some(input);
+            child.stdout = Some(output);
+        }
+        None => {
+            if let Some(InputSource::Bytes(bytes)) = &config.stdin {
+                let (reader, writer) = pipe()?;
+                child.stdin = Some(reader);
+                parent.stdin = Some((writer, bytes.clone()));
+            }
+            if config.stdout == Some(OutputSink::Capture) {
+                let (reader, writer) = pipe()?;
+                child.stdout = Some(writer);
+                parent.stdout = Some(reader);
+            }
+        }
+    }
+    if config.stderr == Some(OutputSink::Capture) {
+        let (reader, writer) = pipe()?;
+        child.stderr = Some(writer);
+        parent.stderr = Some(reader);
+    }
+    Ok((child, parent))
+}
+
+/// A pipe whose ends are closed on `execve`. Returns the read end and the write end.
+fn pipe() -> nix::Result<(OwnedFd, OwnedFd)> {
+    nix::unistd::pipe2(OFlag::O_CLOEXEC)
+}
+
+pub(crate) fn set_nonblocking(fd: &OwnedFd) -> nix::Result<()> {
+    let flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL)?);
+    fcntl(fd, FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK)).map(drop)
+}
+
+/// A standard stream of the program opened in the child, before it is redirected.
+pub(crate) enum Redirect {
+    /// A file opened for the program, closed once it is duplicated.
+    File(File),
+    /// A descriptor kept open by someone else.
+    Fd(RawFd),
+}
+
+impl Redirect {
+    pub(crate) fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Redirect::File(file) => file.as_raw_fd(),
+            Redirect::Fd(fd) => *fd,
+        }
+    }
+}
+
+/// Opens the standard input of the program, unless `piped` replaces it.
+pub(crate) fn open_input(config: &Config, piped: Option<RawFd>) -> io::Result<Redirect> {
+    if let Some(fd) = piped {
+        return Ok(Redirect::Fd(fd));
+    }
+    match &config.stdin {
+        None => File::open(&config.input_path).map(Redirect::File),
+        Some(InputSource::Path(path)) => File::open(path).map(Redirect::File),
+        Some(InputSource::Null) => File::open("/dev/null").map(Redirect::File),
+        Some(InputSource::Fd(fd)) => Ok(Redirect::Fd(*fd)),
+        Some(InputSource::Bytes(_)) => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+    }
+}
+
+/// Opens a standard output or error of the program going to `sink`, or `path` if there is none,
+/// unless `piped` replaces it.
+pub(crate) fn open_output(
+    sink: Option<&OutputSink>,
+    path: &str,
+    piped: Option<RawFd>,
+) -> io::Result<Redirect> {
+    if let Some(fd) = piped {
+        return Ok(Redirect::Fd(fd));
+    }
+    match sink {
+        None => File::create(path).map(Redirect::File),
+        Some(OutputSink::Path(path)) => File::create(path).map(Redirect::File),
+        Some(OutputSink::Null) => OpenOptions::new()
+            .write(true)
+            .open("/dev/null")
+            .map(Redirect::File),
+        Some(OutputSink::Fd(fd)) => Ok(Redirect::Fd(*fd)),
+        Some(OutputSink::Capture) => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+    }
+}
+
+/// Output drained from a pipe.
+#[derive(Debug, Default)]
+pub(crate) struct Captured {
+    /// The output, up to the limit.
+    pub(crate) bytes: Vec<u8>,
+    /// Whether the program wrote more than the limit.
+    pub(crate) exceeded: bool,
+}
+
+impl Captured {
+    /// Keeps what fits of `bytes` below `limit`. Once the limit is exceeded,
+    /// the program behind `program` is killed.
+    pub(crate) fn keep(&mut self, bytes: &[u8], limit: Option<ByteSize>, program: &OwnedFd) {
+        let limit = limit.map_or(usize::MAX, |limit| {
+            usize::try_from(limit.0).unwrap_or(usize::MAX)
+        });
+        let kept = bytes.len().min(limit.saturating_sub(self.bytes.len()));
+        self.bytes.extend_from_slice(&bytes[..kept]);
+        if kept < bytes.len() && !self.exceeded {
+            self.exceeded = true;
+            pidfd_send_signal(program, Signal::SIGKILL);
+        }
+    }
+}
+
+/// How long the threads of a [`Pump`] wait for a pipe before checking whether the program ended.
+const PUMP_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Feeds the standard input and drains the standard output and error of a running program,
+/// each from a thread of its own so that none of them can block another.
+#[derive(Debug)]
+pub(crate) struct Pump {
+    done: Arc<AtomicBool>,
+    stdin: Option<JoinHandle<()>>,
+    stdout: Option<JoinHandle<Captured>>,
+    stderr: Option<JoinHandle<Captured>>,
+}
+
+impl Pump {
+    /// Starts pumping the parent ends of the pipes of the program behind `pidfd`.
+    /// The program is killed once it writes more than `limit`. Output beyond it is dropped.
+    pub(crate) fn start(
+        parent: ParentStdio,
+        limit: Option<ByteSize>,
+        pidfd: &OwnedFd,
+    ) -> io::Result<Option<Pump>> {
+        if parent.stdin.is_none() && parent.stdout.is_none() && parent.stderr.is_none() {
+            return Ok(None);
+        }
+        let done = Arc::new(AtomicBool::new(false));
+        let mut pump = Pump {
+            done: Arc::clone(&done),
+            stdin: None,
+            stdout: None,
+            stderr: None,
+        };
+        if let Some((writer, bytes)) = parent.stdin {
+            set_nonblocking(&writer)?;
+            let done = Arc::clone(&done);
+            pump.stdin = Some(thread::spawn(move || feed(writer, &bytes, &done)));
+        }
+        for (reader, handle) in [
+            (parent.stdout, &mut pump.stdout),
+            (parent.stderr, &mut pump.stderr),
+        ] {
+            if let Some(reader) = reader {
+                set_nonblocking(&reader)?;
+                let done = Arc::clone(&done);
+                let program = pidfd.try_clone()?;
+                *handle = Some(thread::spawn(move || drain(reader, limit, &program, &done)));
+            }
+        }
+        Ok(Some(pump))
+    }
+
+    /// Collects the output once the program has been reaped. Output still buffered in the
+    /// pipes is read, but descendants that keep writing are not waited for.
+    pub(crate) fn finish(mut self) -> (Option<Captured>, Option<Captured>) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(stdin) = self.stdin.take() {
+            let _ = stdin.join();
+        }
+        let join = |handle: Option<JoinHandle<Captured>>| {
+            handle.map(|handle| handle.join().unwrap_or_default())
+        };
+        (join(self.stdout.take()), join(self.stderr.take()))
+    }
+}
+
+impl Drop for Pump {
+    fn drop(&mut self) {
+        // Lets the threads of an abandoned run end on their own.
+        self.done.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Waits up to [`PUMP_INTERVAL`] for `fd` to be ready for `events`.
+fn wait_ready(fd: RawFd, events: libc::c_short) {
+    let mut poll_fd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut poll_fd, 1, PUMP_INTERVAL.as_millis() as i32) };
+}
+
+/// Writes `bytes` to the standard input of the program, then closes it.
+/// Stops early if the program closes its input or has ended.
+fn feed(writer: OwnedFd, bytes: &[u8], done: &AtomicBool) {
+    // A write to a closed pipe fails with EPIPE instead of raising SIGPIPE in the judger.
+    // The pending signal is discarded when the thread exits.
+    let mut sigpipe = SigSet::empty();
+    sigpipe.add(Signal::SIGPIPE);
+    let _ = sigpipe.thread_block();
+    let mut file = File::from(writer);
+    let mut written = 0;
+    while written < bytes.len() {
+        match file.write(&bytes[written..]) {
+            Ok(count) => written += count,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if done.load(Ordering::SeqCst) {
+                    break;
+                }
+                wait_ready(file.as_raw_fd(), libc::POLLOUT);
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+}
+
+/// Reads the standard output or error of the program until it is closed,
+/// or until the pipe is empty once the program has ended.
+/// Kills the program behind `program` once it exceeds `limit`.
+fn drain(
+    reader: OwnedFd,
+    limit: Option<ByteSize>,
+    program: &OwnedFd,
+    done: &AtomicBool,
+) -> Captured {
+    let mut file = File::from(reader);
+    let mut captured = Captured::default();
+    let mut buffer = [0; 8192];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(count) => captured.keep(&buffer[..count], limit, program),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if done.load(Ordering::SeqCst) {
+                    break;
+                }
+                wait_ready(file.as_raw_fd(), libc::POLLIN);
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+    captured
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "async")]
+
+use judger::{ByteSize, Config, ErrorCode, InputSource, OutputSink, SeccompRuleName, run_async};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+fn sleep_config(name: &str, seconds: &str) -> Config {
+    Config {
+        exe_path: "/bin/sleep".to_string(),
+        args: vec!["/bin/sleep".to_string(), seconds.to_string()],
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: "/dev/null".to_string(),
+        log_path: format!("{}.log", name),
+        max_memory: None,
+        seccomp_rule_name: None,
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_async_concurrent_runs() {
+    let config = Config {
+        max_real_time: Some(Duration::from_secs(5)),
+        ..sleep_config("async_concurrent", "0.5")
+    };
+    let start = Instant::now();
+    let mut runs = tokio::task::JoinSet::new();
+    for _ in 0..20 {
+        let config = config.clone();
+        runs.spawn(async move { run_async(&config, None).await });
+    }
+    let results = runs.join_all().await;
+    // All runs are supervised by the single runtime thread at the same time.
+    assert!(start.elapsed() < Duration::from_secs(4));
+    for result in results {
+        let result = result.unwrap();
+        assert_eq!(result.result, ErrorCode::Success);
+        assert!(result.real_time >= Duration::from_millis(500));
+    }
+    let _ = std::fs::remove_file("async_concurrent.log");
+}
+
+#[tokio::test]
+async fn test_async_limits() {
+    let config = Config {
+        max_real_time: Some(Duration::from_millis(200)),
+        ..sleep_config("async_limits", "5")
+    };
+    let result = run_async(&config, None).await.unwrap();
+    assert_eq!(result.result, ErrorCode::RealTimeLimitExceeded);
+    assert!(result.real_time < Duration::from_secs(2));
+
+    let config = Config {
+        exe_path: "/bin/sh".to_string(),
+        args: vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            "while :; do :; done".to_string(),
+        ],
+        max_cpu_time: Some(Duration::from_millis(300)),
+        max_real_time: Some(Duration::from_secs(10)),
+        ..sleep_config("async_limits", "0")
+    };
+    let result = run_async(&config, None).await.unwrap();
+    assert_eq!(result.result, ErrorCode::CpuTimeLimitExceeded);
+    assert!(result.cpu_time < Duration::from_millis(800));
+    let _ = std::fs::remove_file("async_limits.log");
+}
+
+#[tokio::test]
+async fn test_async_drop_kills() {
+    let config = Config {
+        exe_path: "/bin/sh".to_string(),
+        args: vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            "echo $$; exec sleep 30".to_string(),
+        ],
+        output_path: "async_drop.out".to_string(),
+        ..sleep_config("async_drop", "0")
+    };
+    let timed_out =
+        tokio::time::timeout(Duration::from_millis(300), run_async(&config, None)).await;
+    assert!(timed_out.is_err());
+    let pid = std::fs::read_to_string("async_drop.out").unwrap();
+    let pid = pid.trim();
+    assert!(!pid.is_empty());
+    // Dropping the future killed and reaped the program.
+    assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+    let _ = std::fs::remove_file("async_drop.out");
+    let _ = std::fs::remove_file("async_drop.log");
+}
+
+#[tokio::test]
+async fn test_async_syscall_violation() {
+    let tmp_file_path = "./async_syscall.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    // Without a tracer, a caught SIGSYS must not let the program carry on.
+    let code = r#"#include <signal.h>
+#include <sys/socket.h>
+static void ignore(int signal) { (void)signal; }
+int main() {
+    signal(SIGSYS, ignore);
+    socket(AF_INET, SOCK_STREAM, 0);
+    return 0;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "async_syscall"])
+        .output();
+
+    let config = Config {
+        exe_path: "async_syscall".to_string(),
+        args: vec![],
+        seccomp_rule_name: Some(SeccompRuleName::CCpp),
+        ..sleep_config("async_syscall", "0")
+    };
+    let result = run_async(&config, None).await.unwrap();
+    assert_eq!(result.result, ErrorCode::SyscallViolation);
+    assert_eq!(result.syscall, None);
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("async_syscall");
+    let _ = std::fs::remove_file("async_syscall.log");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_async_in_memory_stdio() {
+    // Larger than a pipe buffer in both directions, so feeding and draining must overlap
+    // on the single runtime thread.
+    let input: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let config = Config {
+        exe_path: "/bin/cat".to_string(),
+        args: vec![],
+        stdin: Some(InputSource::Bytes(input.clone())),
+        stdout: Some(OutputSink::Capture),
+        stderr: Some(OutputSink::Capture),
+        max_output_size: Some(ByteSize::mib(4)),
+        ..sleep_config("async_stdio", "0")
+    };
+    let result = run_async(&config, None).await.unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+    assert_eq!(result.stdout.as_deref(), Some(input.as_slice()));
+    assert_eq!(result.stderr.as_deref(), Some(&b""[..]));
+
+    // Input left over when the program exits is dropped.
+    let early = Config {
+        exe_path: "/bin/true".to_string(),
+        ..config.clone()
+    };
+    let result = run_async(&early, None).await.unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+
+    // A program that keeps writing is killed once it exceeds the limit.
+    let config = Config {
+        exe_path: "/usr/bin/yes".to_string(),
+        stdin: Some(InputSource::Null),
+        max_output_size: Some(ByteSize(1000)),
+        max_real_time: Some(Duration::from_secs(10)),
+        ..config
+    };
+    let result = run_async(&config, None).await.unwrap();
+    assert_eq!(result.result, ErrorCode::OutputLimitExceeded);
+    assert_eq!(result.stdout.map(|stdout| stdout.len()), Some(1000));
+    assert!(result.real_time < Duration::from_secs(5));
+    let _ = std::fs::remove_file("async_stdio.log");
+}
@@ -0,0 +1,83 @@
+use judger::{BatchOptions, Config, ErrorCode, SeccompRuleName, TestCase, run_batch};
+use std::io::Write;
+
+fn write_file(path: &str, content: &str) {
+    let mut file = std::fs::File::create(path).expect("Unable to create file");
+    file.write_all(content.as_bytes())
+        .expect("Unable to write data");
+}
+
+#[test]
+fn test_batch() {
+    let tmp_file_path = "./batch.c";
+    write_file(
+        tmp_file_path,
+        r#"#include <stdio.h>
+int main() {
+    int a, b;
+    scanf("%d %d", &a, &b);
+    printf("%d\n", a + b);
+    return 0;
+}"#,
+    );
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "batch"])
+        .output();
+
+    let mut cases = vec![];
+    for (index, (input, answer)) in [("1 2\n", "3\n"), ("2 2\n", "5\n"), ("5 5\n", "10\n")]
+        .iter()
+        .enumerate()
+    {
+        write_file(&format!("batch{}.in", index), input);
+        write_file(&format!("batch{}.ans", index), answer);
+        cases.push(TestCase {
+            input_path: format!("batch{}.in", index),
+            output_path: format!("batch{}.out", index),
+            answer_path: Some(format!("batch{}.ans", index)),
+        });
+    }
+
+    let config = Config {
+        exe_path: "batch".to_string(),
+        error_path: "batch.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: Some(SeccompRuleName::CCpp),
+        ..Default::default()
+    };
+
+    let options = BatchOptions {
+        stop_on_failure: false,
+        concurrency: 2,
+    };
+    let results = run_batch(&config, None, &cases, options).unwrap();
+    let verdicts: Vec<i32> = results
+        .iter()
+        .map(|result| result.result.to_i32())
+        .collect();
+    assert_eq!(verdicts, vec![0, 5, 0]);
+    // Concurrent cases write their standard error to separate files.
+    for index in 0..3 {
+        assert!(std::path::Path::new(&format!("batch.err.{}", index)).exists());
+    }
+
+    let options = BatchOptions {
+        stop_on_failure: true,
+        concurrency: 1,
+    };
+    let results = run_batch(&config, None, &cases, options).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(matches!(results[1].result, ErrorCode::WrongAnswer(_)));
+
+    // clean up
+    for index in 0..3 {
+        let _ = std::fs::remove_file(format!("batch{}.in", index));
+        let _ = std::fs::remove_file(format!("batch{}.out", index));
+        let _ = std::fs::remove_file(format!("batch{}.ans", index));
+        let _ = std::fs::remove_file(format!("batch.err.{}", index));
+    }
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("batch");
+    let _ = std::fs::remove_file("batch.err");
+    let _ = std::fs::remove_file("judger.log");
+}
@@ -0,0 +1,59 @@
+use judger::{CgroupConfig, Config, ErrorCode, SeccompRuleName, run};
+use std::io::Write;
+
+#[test]
+fn test_cgroup_mle() {
+    if !std::path::Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+        println!("cgroup v2 is not available, skipping");
+        return;
+    }
+    let tmp_file_path = "./cgroup_mle.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let mle_code = r#"
+#include <stdlib.h>
+#include <string.h>
+int main() {
+    char *arr = (char*)malloc(256 * 1024 * 1024);
+    if (arr == NULL) {
+        return 1;
+    }
+    memset(arr, 1, 256 * 1024 * 1024);
+    return arr[1024];
+}"#;
+    file.write_all(mle_code.as_bytes())
+        .expect("Unable to write data");
+    let input_file_path = "cgroup_mle.in";
+    let mut input_file =
+        std::fs::File::create(input_file_path).expect("Unable to create input file");
+    input_file
+        .write_all("\n".as_bytes())
+        .expect("Unable to write input data");
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "cgroup_mle"])
+        .output();
+    let config = Config {
+        exe_path: "cgroup_mle".to_string(),
+        input_path: input_file_path.to_string(),
+        output_path: "cgroup_mle.out".to_string(),
+        error_path: "cgroup_mle.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: Some(SeccompRuleName::CCpp),
+        cgroup: Some(CgroupConfig {
+            parent: "/sys/fs/cgroup/judger-test".to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let result = run(&config, None);
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    println!("{:?}", result);
+    assert_eq!(result.result, ErrorCode::MemoryLimitExceeded);
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file(input_file_path);
+    let _ = std::fs::remove_file("cgroup_mle");
+    let _ = std::fs::remove_file("cgroup_mle.out");
+    let _ = std::fs::remove_file("cgroup_mle.err");
+    let _ = std::fs::remove_file("judger.log");
+}
@@ -0,0 +1,96 @@
+use judger::{CheckMode, Config, ErrorCode, SeccompRuleName, check, run};
+use std::io::Write;
+
+fn write_file(path: &str, content: &str) {
+    let mut file = std::fs::File::create(path).expect("Unable to create file");
+    file.write_all(content.as_bytes())
+        .expect("Unable to write data");
+}
+
+#[test]
+fn test_check_modes() {
+    write_file("check_modes.out", "1 2  \n3.0000001\n\n");
+    write_file("check_modes.ans", "1 2\n3\n");
+
+    let result = check("check_modes.out", "check_modes.ans", CheckMode::Exact);
+    assert!(matches!(result, Ok(ErrorCode::WrongAnswer(_))));
+    let result = check(
+        "check_modes.out",
+        "check_modes.ans",
+        CheckMode::IgnoreTrailingWhitespace,
+    );
+    assert_eq!(
+        result.unwrap(),
+        ErrorCode::WrongAnswer("line 2: expected `3`, found `3.0000001`".to_string())
+    );
+    let result = check("check_modes.out", "check_modes.ans", CheckMode::Token);
+    assert!(matches!(result, Ok(ErrorCode::WrongAnswer(_))));
+    let result = check(
+        "check_modes.out",
+        "check_modes.ans",
+        CheckMode::Float {
+            absolute: 1e-6,
+            relative: 1e-6,
+        },
+    );
+    assert_eq!(result.unwrap(), ErrorCode::Success);
+    // clean up
+    let _ = std::fs::remove_file("check_modes.out");
+    let _ = std::fs::remove_file("check_modes.ans");
+}
+
+#[test]
+fn test_run_with_answer() {
+    let tmp_file_path = "./answer.c";
+    write_file(
+        tmp_file_path,
+        r#"#include <stdio.h>
+int main() {
+    int a, b;
+    scanf("%d %d", &a, &b);
+    printf("%d\n", a + b);
+    return 0;
+}"#,
+    );
+    write_file("answer.in", "1 2\n");
+    write_file("answer_ok.ans", "3\n");
+    write_file("answer_wrong.ans", "4\n");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "answer"])
+        .output();
+
+    let config = Config {
+        exe_path: "answer".to_string(),
+        input_path: "answer.in".to_string(),
+        output_path: "answer.out".to_string(),
+        error_path: "answer.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: Some(SeccompRuleName::CCpp),
+        answer_path: Some("answer_ok.ans".to_string()),
+        ..Default::default()
+    };
+    let result = run(&config, None);
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().result, ErrorCode::Success);
+
+    let config = Config {
+        answer_path: Some("answer_wrong.ans".to_string()),
+        ..config
+    };
+    let result = run(&config, None);
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap().result,
+        ErrorCode::WrongAnswer("line 1: expected `4`, found `3`".to_string())
+    );
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("answer");
+    let _ = std::fs::remove_file("answer.in");
+    let _ = std::fs::remove_file("answer_ok.ans");
+    let _ = std::fs::remove_file("answer_wrong.ans");
+    let _ = std::fs::remove_file("answer.out");
+    let _ = std::fs::remove_file("answer.err");
+    let _ = std::fs::remove_file("judger.log");
+}
@@ -0,0 +1,144 @@
+use judger::{Config, ErrorCode, compile, run};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+/// A directory the compiler, which runs as `nobody`, can read from and write to.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    std::fs::create_dir_all(&dir).expect("Unable to create directory");
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o1777))
+        .expect("Unable to open up directory");
+    dir
+}
+
+#[test]
+fn test_compile() {
+    let dir = scratch_dir("judger_compile_ok");
+    let tmp_file_path = dir.join("compile_ok.c");
+    let tmp_file_path = tmp_file_path.to_str().expect("Invalid path");
+    let exe_path = dir.join("compile_ok");
+    let exe_path = exe_path.to_str().expect("Invalid path");
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let code = r#"#include <stdio.h>
+int main() {
+    printf("Hello World\n");
+    return 0;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+
+    let config = Config {
+        exe_path: "/usr/bin/gcc".to_string(),
+        args: vec![
+            "/usr/bin/gcc".to_string(),
+            tmp_file_path.to_string(),
+            "-o".to_string(),
+            exe_path.to_string(),
+        ],
+        input_path: "/dev/null".to_string(),
+        output_path: "compile_ok.out".to_string(),
+        error_path: "compile_ok.err".to_string(),
+        log_path: "judger.log".to_string(),
+        ..Config::compiler()
+    };
+
+    let result = compile(&config);
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    println!("{:?}", result);
+    assert_eq!(result.result, ErrorCode::Success);
+    assert!(std::path::Path::new(exe_path).exists());
+    // clean up
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_file("compile_ok.out");
+    let _ = std::fs::remove_file("compile_ok.err");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_compile_error() {
+    let dir = scratch_dir("judger_compile_error");
+    let tmp_file_path = dir.join("compile_error.c");
+    let tmp_file_path = tmp_file_path.to_str().expect("Invalid path");
+    let exe_path = dir.join("compile_error");
+    let exe_path = exe_path.to_str().expect("Invalid path");
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let code = r#"int main() {
+    return undefined_variable;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+
+    let config = Config {
+        exe_path: "/usr/bin/gcc".to_string(),
+        args: vec![
+            "/usr/bin/gcc".to_string(),
+            tmp_file_path.to_string(),
+            "-o".to_string(),
+            exe_path.to_string(),
+        ],
+        input_path: "/dev/null".to_string(),
+        output_path: "compile_error.out".to_string(),
+        error_path: "compile_error.err".to_string(),
+        log_path: "judger.log".to_string(),
+        ..Config::compiler()
+    };
+
+    let result = compile(&config);
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    println!("{:?}", result);
+    match result.result {
+        ErrorCode::CompileError(diagnostics) => assert!(diagnostics.contains("undefined_variable")),
+        other => panic!("Expected CompileError, got {:?}", other),
+    }
+    // clean up
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_file("compile_error.out");
+    let _ = std::fs::remove_file("compile_error.err");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_compiler_rule_denies_signals() {
+    let dir = scratch_dir("judger_compile_signal");
+    let tmp_file_path = dir.join("compile_signal.c");
+    let tmp_file_path = tmp_file_path.to_str().expect("Invalid path");
+    let exe_path = dir.join("compile_signal");
+    let exe_path = exe_path.to_str().expect("Invalid path");
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let code = r#"#include <sys/syscall.h>
+#include <unistd.h>
+int main() {
+    syscall(SYS_pidfd_open, getppid(), 0);
+    return 0;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", exe_path])
+        .output();
+
+    let config = Config {
+        exe_path: exe_path.to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "compile_signal.out".to_string(),
+        error_path: "compile_signal.err".to_string(),
+        log_path: "judger.log".to_string(),
+        ..Config::compiler()
+    };
+
+    let result = run(&config, None);
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    println!("{:?}", result);
+    assert_eq!(result.result, ErrorCode::SyscallViolation);
+    assert_eq!(result.syscall.as_deref(), Some("pidfd_open"));
+    // clean up
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_file("compile_signal.out");
+    let _ = std::fs::remove_file("compile_signal.err");
+    let _ = std::fs::remove_file("judger.log");
+}
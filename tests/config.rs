@@ -0,0 +1,192 @@
+use judger::{
+    ByteSize, CONFIG_SCHEMA, CgroupConfig, CheckMode, Config, MemoryLimitMode, NOBODY, RootFs,
+    RunResult,
+};
+use serde_json::Value;
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+#[test]
+fn test_config_from_file() {
+    let mut file = std::fs::File::create("config.json").expect("Unable to create file");
+    file.write_all(
+        br#"{
+    "max_cpu_time": 2000,
+    "exe_path": "main",
+    "memory_limit_mode": "check_only",
+    "seccomp_rule_name": "c-cpp",
+    "namespaces": { "net": true },
+    "check_mode": { "float": { "absolute": 0.001, "relative": 0.001 } }
+}"#,
+    )
+    .expect("Unable to write data");
+    let config = Config::from_file("config.json").unwrap();
+    assert_eq!(config.max_cpu_time, Some(Duration::from_secs(2)));
+    assert_eq!(config.exe_path, "main");
+    assert_eq!(config.memory_limit_mode, MemoryLimitMode::CheckOnly);
+    assert!(config.namespaces.net && !config.namespaces.pid);
+    assert!(matches!(config.check_mode, CheckMode::Float { .. }));
+    // Missing fields keep their defaults.
+    assert_eq!(config.max_real_time, Config::default().max_real_time);
+
+    let mut file = std::fs::File::create("config.toml").expect("Unable to create file");
+    file.write_all(
+        br#"exe_path = "main"
+max_memory = 268435456
+
+[cgroup]
+parent = "/sys/fs/cgroup/judger-test"
+cpu_quota = -1
+
+[rootfs]
+tmp_size = -1
+"#,
+    )
+    .expect("Unable to write data");
+    let config = Config::from_file("config.toml").unwrap();
+    assert_eq!(config.max_memory, Some(ByteSize::mib(256)));
+    assert_eq!(
+        config.cgroup.as_ref().map(|cgroup| cgroup.parent.as_str()),
+        Some("/sys/fs/cgroup/judger-test")
+    );
+    assert_eq!(config.cgroup.and_then(|cgroup| cgroup.cpu_quota), None);
+    assert_eq!(config.rootfs.and_then(|rootfs| rootfs.tmp_size), None);
+
+    let mut file = std::fs::File::create("config.json").expect("Unable to create file");
+    file.write_all(br#"{ "max_cpu_tme": 2000 }"#)
+        .expect("Unable to write data");
+    assert!(Config::from_file("config.json").is_err());
+    // clean up
+    let _ = std::fs::remove_file("config.json");
+    let _ = std::fs::remove_file("config.toml");
+}
+
+#[test]
+fn test_config_schema() {
+    let schema: Value = serde_json::from_str(CONFIG_SCHEMA).unwrap();
+    let config = Config {
+        cgroup: Some(CgroupConfig::default()),
+        rootfs: Some(RootFs::default()),
+        special_judge: Some(Box::new(Config::default())),
+        ..Default::default()
+    };
+    let config = serde_json::to_value(&config).unwrap();
+
+    // Every serialized field, including those of nested settings, is described by the schema.
+    for (definition, value) in [
+        ("Config", &config),
+        ("CgroupConfig", &config["cgroup"]),
+        ("RootFs", &config["rootfs"]),
+        ("BindMount", &config["rootfs"]["bind_mounts"][0]),
+        ("Namespaces", &config["namespaces"]),
+    ] {
+        let properties = schema["$defs"][definition]["properties"]
+            .as_object()
+            .unwrap();
+        let fields = value.as_object().unwrap();
+        let mut expected: Vec<&String> = fields.keys().collect();
+        let mut described: Vec<&String> = properties.keys().collect();
+        expected.sort();
+        described.sort();
+        assert_eq!(
+            expected, described,
+            "schema of {} is out of date",
+            definition
+        );
+    }
+
+    // The config round-trips through JSON.
+    let parsed: Config = serde_json::from_value(config.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), config);
+}
+
+#[test]
+fn test_config_builder() {
+    let config = Config::builder("/bin/echo")
+        .args(["hello"])
+        .max_cpu_time(Duration::from_millis(1500))
+        .max_real_time(None)
+        .max_memory(ByteSize::mib(64))
+        .max_process_number(NonZeroU32::new(4))
+        .build()
+        .unwrap();
+    assert_eq!(config.args, ["/bin/echo", "hello"]);
+    assert_eq!(config.max_cpu_time, Some(Duration::from_millis(1500)));
+    assert_eq!(config.max_real_time, None);
+    assert_eq!(config.max_memory, Some(ByteSize::mib(64)));
+    assert_eq!(config.max_process_number, NonZeroU32::new(4));
+    assert_eq!(config.input_path, "/dev/null");
+    assert_eq!((config.uid, config.gid), (NOBODY, NOBODY));
+    let config = Config::builder("/bin/true").user(0, 0).build().unwrap();
+    assert_eq!((config.uid, config.gid), (0, 0));
+
+    // Every invalid field is reported, including those of a special judge.
+    let errors = Config::builder("/nonexistent/program")
+        .argv0("")
+        .max_cpu_time(Duration::ZERO)
+        .max_stack(ByteSize(0))
+        .user(u32::MAX, 0)
+        .special_judge(Config {
+            max_output_size: Some(ByteSize(0)),
+            ..Config::builder("/bin/true").build().unwrap()
+        })
+        .build()
+        .unwrap_err();
+    let mut fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+    fields.sort();
+    assert_eq!(
+        fields,
+        [
+            "args",
+            "exe_path",
+            "max_cpu_time",
+            "max_stack",
+            "special_judge.max_output_size",
+            "uid"
+        ]
+    );
+}
+
+#[test]
+fn test_legacy_limits() {
+    // Config files keep the integer layout, with -1 for unlimited.
+    let config: Config = serde_json::from_str(
+        r#"{ "max_real_time": -1, "max_stack": -1, "max_output_size": 4096 }"#,
+    )
+    .unwrap();
+    assert_eq!(config.max_real_time, None);
+    assert_eq!(config.max_stack, None);
+    assert_eq!(config.max_output_size, Some(ByteSize::kib(4)));
+    assert!(serde_json::from_str::<Config>(r#"{ "max_memory": -2 }"#).is_err());
+    assert!(serde_json::from_str::<Config>(r#"{ "max_process_number": 0 }"#).is_err());
+    let value = serde_json::to_value(&config).unwrap();
+    assert_eq!(value["max_cpu_time"], 1000);
+    assert_eq!(value["max_real_time"], -1);
+
+    let mut limits = config.legacy_limits();
+    assert_eq!(limits.max_cpu_time, 1000);
+    assert_eq!(limits.max_stack, -1);
+    limits.max_memory = 1 << 40;
+    limits.max_process_number = -1;
+    let mut config = config;
+    config.set_legacy_limits(limits).unwrap();
+    assert_eq!(config.max_memory, Some(ByteSize::gib(1024)));
+    assert_eq!(config.max_process_number, None);
+
+    limits.max_process_number = 0;
+    limits.cpu_time_grace = -1;
+    let errors = config.set_legacy_limits(limits).unwrap_err();
+    let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+    assert_eq!(fields, ["cpu_time_grace", "max_process_number"]);
+
+    // Usage is reported in the same milliseconds and bytes.
+    let result = RunResult {
+        cpu_time: Duration::from_millis(1500),
+        memory: ByteSize::mib(3),
+        ..Default::default()
+    };
+    let value = serde_json::to_value(&result).unwrap();
+    assert_eq!(value["cpu_time"], 1500);
+    assert_eq!(value["memory"], 3 * 1024 * 1024);
+}
@@ -0,0 +1,96 @@
+use judger::{Config, ErrorCode, run};
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+#[test]
+fn test_descendant_cpu_time() {
+    let tmp_file_path = "./cpu_fork.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let code = r#"#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+int main() {
+    if (fork() == 0) {
+        clock_t start = clock();
+        while (clock() - start < CLOCKS_PER_SEC / 2) {}
+        return 0;
+    }
+    wait(NULL);
+    return 0;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "cpu_fork"])
+        .output();
+
+    let config = Config {
+        exe_path: "cpu_fork".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "cpu_fork.out".to_string(),
+        error_path: "cpu_fork.err".to_string(),
+        log_path: "judger.log".to_string(),
+        max_process_number: NonZeroU32::new(2),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+    assert!(result.cpu_time < Duration::from_millis(250));
+    assert_eq!(result.cpu_time, result.user_time + result.system_time);
+
+    let config = Config {
+        include_descendant_cpu_time: true,
+        ..config
+    };
+    let result = run(&config, None).unwrap();
+    assert!(result.cpu_time >= Duration::from_millis(450));
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("cpu_fork");
+    let _ = std::fs::remove_file("cpu_fork.out");
+    let _ = std::fs::remove_file("cpu_fork.err");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_cpu_time_killed_precisely() {
+    let tmp_file_path = "./cpu_spin.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let code = r#"int main() {
+    volatile unsigned long long i = 0;
+    for (;;) i++;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "cpu_spin"])
+        .output();
+
+    let config = Config {
+        exe_path: "cpu_spin".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "cpu_spin.out".to_string(),
+        error_path: "cpu_spin.err".to_string(),
+        log_path: "judger.log".to_string(),
+        max_cpu_time: Some(Duration::from_millis(300)),
+        max_real_time: Some(Duration::from_secs(10)),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::CpuTimeLimitExceeded);
+    // RLIMIT_CPU alone would only stop the program after a full second.
+    assert!(result.cpu_time < Duration::from_millis(600));
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("cpu_spin");
+    let _ = std::fs::remove_file("cpu_spin.out");
+    let _ = std::fs::remove_file("cpu_spin.err");
+    let _ = std::fs::remove_file("judger.log");
+}
@@ -0,0 +1,63 @@
+use judger::{Config, ErrorCode, spawn};
+use std::time::{Duration, Instant};
+
+fn sleep_config(seconds: &str) -> Config {
+    Config {
+        exe_path: "/bin/sleep".to_string(),
+        args: vec!["/bin/sleep".to_string(), seconds.to_string()],
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: "/dev/null".to_string(),
+        log_path: "handle.log".to_string(),
+        max_memory: None,
+        seccomp_rule_name: None,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_handle_kill() {
+    let config = Config {
+        max_real_time: Some(Duration::from_secs(30)),
+        ..sleep_config("30")
+    };
+    let start = Instant::now();
+    let mut handle = spawn(&config, None).unwrap();
+    let proc_path = format!("/proc/{}", handle.pid());
+    assert!(std::path::Path::new(&proc_path).exists());
+    assert!(handle.try_wait().unwrap().is_none());
+
+    handle.kill();
+    let result = handle.wait().unwrap();
+    assert_eq!(result.result, ErrorCode::Cancelled);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!std::path::Path::new(&proc_path).exists());
+    let _ = std::fs::remove_file("handle.log");
+}
+
+#[test]
+fn test_handle_wait() {
+    let mut handle = spawn(&sleep_config("0.1"), None).unwrap();
+    let result = loop {
+        if let Some(result) = handle.try_wait().unwrap() {
+            break result;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(result.result, ErrorCode::Success);
+    // A run that already finished keeps its result.
+    handle.kill();
+    let waited = handle.wait().unwrap();
+    assert_eq!(waited.result, ErrorCode::Success);
+    assert_eq!(waited.real_time, result.real_time);
+
+    let config = Config {
+        exe_path: "does_not_exist".to_string(),
+        ..sleep_config("0")
+    };
+    assert!(matches!(
+        spawn(&config, None),
+        Err(judger::JudgerError::InvalidConfig(_))
+    ));
+    let _ = std::fs::remove_file("handle.log");
+}
@@ -1,6 +1,16 @@
-use judger::{Config, SeccompRuleName, run};
+use judger::{Config, ErrorCode, SeccompRuleName, run};
 use std::io::Write;
-use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn interactor(exe_path: &str, error_path: &str) -> Config {
+    Config {
+        exe_path: exe_path.to_string(),
+        error_path: error_path.to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: Some(SeccompRuleName::CCppFileIO),
+        ..Default::default()
+    }
+}
 
 #[test]
 fn test_interactor() {
@@ -41,18 +51,21 @@ int main(){
         ..Default::default()
     };
 
-    let result = run(&config, Some(PathBuf::from("assets/interactor")));
-    // let result = run(&config, None);
+    let interactor = interactor("assets/interactor", "interactor.err");
+    let result = run(&config, Some(&interactor));
     println!("{:?}", result);
     assert!(result.is_ok());
     let result = result.unwrap();
-    assert_eq!(result.result, judger::ErrorCode::Success);
+    assert_eq!(result.result, ErrorCode::Success);
+    assert_eq!(result.score, Some(1.0));
+    assert!(result.interactor.is_some());
     // clean up
     let _ = std::fs::remove_file(tmp_file_path);
     let _ = std::fs::remove_file(input_file_path);
     let _ = std::fs::remove_file("user");
     let _ = std::fs::remove_file("user.out");
     let _ = std::fs::remove_file("user.err");
+    let _ = std::fs::remove_file("interactor.err");
     let _ = std::fs::remove_file("judger.log");
 }
 
@@ -95,15 +108,14 @@ int main(){
         ..Default::default()
     };
 
-    let result = run(&config, Some(PathBuf::from("assets/interactor")));
+    let interactor = interactor("assets/interactor", "interactor_wrong.err");
+    let result = run(&config, Some(&interactor));
     println!("{:?}", result);
     assert!(result.is_ok());
     let result = result.unwrap();
     assert_eq!(
         result.result,
-        judger::ErrorCode::WrongAnswer(
-            "wrong answer Query 1: expected 30, found -10\n".to_string()
-        )
+        ErrorCode::WrongAnswer("wrong answer Query 1: expected 30, found -10".to_string())
     );
     // clean up
     let _ = std::fs::remove_file(tmp_file_path);
@@ -111,5 +123,80 @@ int main(){
     let _ = std::fs::remove_file("user_wrong");
     let _ = std::fs::remove_file("user_wrong.out");
     let _ = std::fs::remove_file("user_wrong.err");
+    let _ = std::fs::remove_file("interactor_wrong.err");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_interactor_crash() {
+    let tmp_file_path = "./crashing_interactor.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let interactor_code = r#"#include <stdlib.h>
+int main() {
+    abort();
+}"#;
+    file.write_all(interactor_code.as_bytes())
+        .expect("Unable to write data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "crashing_interactor"])
+        .output();
+
+    let config = Config {
+        exe_path: "/bin/true".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "crash.out".to_string(),
+        error_path: "crash.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+
+    let interactor = interactor("crashing_interactor", "crashing_interactor.err");
+    let result = run(&config, Some(&interactor));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    // The crash is a judge failure, not a verdict on the program.
+    assert_eq!(result.result, ErrorCode::SpjError);
+    assert!(result.interactor.is_some());
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("crashing_interactor");
+    let _ = std::fs::remove_file("crashing_interactor.err");
+    let _ = std::fs::remove_file("crash.out");
+    let _ = std::fs::remove_file("crash.err");
     let _ = std::fs::remove_file("judger.log");
 }
+
+#[test]
+fn test_interactor_timeout() {
+    std::fs::write("pair_timeout.in", "1\n").expect("Unable to create input file");
+    let shell = |script: &str, error_path: &str| Config {
+        exe_path: "/bin/sh".to_string(),
+        args: vec!["/bin/sh".to_string(), "-c".to_string(), script.to_string()],
+        input_path: "pair_timeout.in".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: error_path.to_string(),
+        log_path: "pair_timeout.log".to_string(),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+    let config = Config {
+        max_real_time: Some(Duration::from_millis(500)),
+        ..shell("read line", "pair_timeout.err")
+    };
+    // The interactor neither talks nor notices that the program is gone.
+    let interactor = Config {
+        max_real_time: None,
+        ..shell("sleep 30", "pair_timeout_interactor.err")
+    };
+    let start = Instant::now();
+    let result = run(&config, Some(&interactor)).unwrap();
+    assert_eq!(result.result, ErrorCode::RealTimeLimitExceeded);
+    // The interactor is stopped with the program.
+    assert!(start.elapsed() < Duration::from_secs(5));
+    let _ = std::fs::remove_file("pair_timeout.in");
+    let _ = std::fs::remove_file("pair_timeout.err");
+    let _ = std::fs::remove_file("pair_timeout_interactor.err");
+    let _ = std::fs::remove_file("pair_timeout.log");
+}
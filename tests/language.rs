@@ -0,0 +1,71 @@
+use judger::{Config, LanguageRegistry, MemoryLimitMode};
+use std::io::Write;
+use std::time::Duration;
+
+#[test]
+fn test_language_profiles() {
+    let mut registry = LanguageRegistry::default();
+    for name in ["c", "cpp", "java", "python", "go", "node", "rust"] {
+        assert!(registry.get(name).is_some(), "missing profile {}", name);
+    }
+
+    let java = registry.get("java").unwrap();
+    assert!(java.compile_config("Main.java", "main").is_none());
+    let config = java.run_config(
+        "Main.java",
+        "main",
+        Config {
+            max_cpu_time: Some(Duration::from_secs(1)),
+            max_real_time: None,
+            ..Default::default()
+        },
+    );
+    assert_eq!(config.max_cpu_time, Some(Duration::from_secs(2)));
+    assert_eq!(config.max_real_time, None);
+    assert_eq!(config.memory_limit_mode, MemoryLimitMode::CheckOnly);
+    assert_eq!(config.args.last().map(String::as_str), Some("Main.java"));
+
+    let c = registry.get("c").unwrap();
+    let compile = c.compile_config("main.c", "main").unwrap();
+    assert_eq!(compile.exe_path, "/usr/bin/gcc");
+    assert!(compile.args.contains(&"main.c".to_string()));
+    assert_eq!(compile.env, Config::compiler().env);
+
+    let go = registry.get("go").unwrap();
+    let compile = go.compile_config("main.go", "main").unwrap();
+    assert_eq!(
+        compile.memory_limit_mode,
+        Config::compiler().memory_limit_mode
+    );
+    assert_eq!(
+        compile
+            .env
+            .iter()
+            .filter(|var| var.starts_with("PATH="))
+            .count(),
+        1
+    );
+    assert!(
+        compile
+            .env
+            .contains(&"PATH=/usr/local/go/bin:/usr/bin:/bin".to_string())
+    );
+
+    let mut file = std::fs::File::create("languages.toml").expect("Unable to create file");
+    file.write_all(
+        br#"[[languages]]
+name = "c"
+compile = ["/usr/bin/clang", "-o", "{exe}", "{source}"]
+run = ["{exe}"]
+seccomp_rule_name = "c-cpp-file-io"
+time_multiplier = 1.5
+"#,
+    )
+    .expect("Unable to write data");
+    registry.load_file("languages.toml").unwrap();
+    let c = registry.get("c").unwrap();
+    assert_eq!(c.compile[0], "/usr/bin/clang");
+    assert_eq!(c.time_multiplier, 1.5);
+    // clean up
+    let _ = std::fs::remove_file("languages.toml");
+}
@@ -1,5 +1,6 @@
-use judger::{Config, ErrorCode, SeccompRuleName, run};
+use judger::{ByteSize, Config, ErrorCode, MemoryLimitMode, SeccompRuleName, run};
 use std::io::Write;
+use std::time::Duration;
 
 #[test]
 fn test_tle() {
@@ -200,7 +201,7 @@ int main() {
     assert!(result.is_ok());
     let result = result.unwrap();
     println!("{:?}", result);
-    assert_eq!(result.result, ErrorCode::RuntimeError);
+    assert_eq!(result.result, ErrorCode::OutputLimitExceeded);
     // clean up
     let _ = std::fs::remove_file(tmp_file_path);
     let _ = std::fs::remove_file(input_file_path);
@@ -209,3 +210,138 @@ int main() {
     let _ = std::fs::remove_file("1.err");
     let _ = std::fs::remove_file("judger.log");
 }
+
+#[test]
+fn test_rtle() {
+    let tmp_file_path = "./rtle.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let rtle_code = r#"
+#include <unistd.h>
+int main() {
+    sleep(10);
+    return 0;
+}"#;
+    file.write_all(rtle_code.as_bytes())
+        .expect("Unable to write data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "rtle"])
+        .output();
+
+    let config = Config {
+        exe_path: "rtle".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "rtle.out".to_string(),
+        error_path: "rtle.out".to_string(),
+        log_path: "judger.log".to_string(),
+        max_real_time: Some(Duration::from_millis(300)),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::RealTimeLimitExceeded);
+    assert!(result.real_time < Duration::from_secs(1));
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("rtle");
+    let _ = std::fs::remove_file("rtle.out");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_mle_check_only() {
+    let tmp_file_path = "./mle_check_only.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    // Reserves far more address space than it touches, like a managed runtime.
+    let mle_code = r#"
+#include <string.h>
+#include <sys/mman.h>
+int main() {
+    char *arr = mmap(NULL, 4096L * 1024 * 1024, PROT_READ | PROT_WRITE,
+                     MAP_PRIVATE | MAP_ANONYMOUS | MAP_NORESERVE, -1, 0);
+    if (arr == MAP_FAILED) {
+        return 1;
+    }
+    memset(arr, 1, 200 * 1024 * 1024);
+    return 0;
+}"#;
+    file.write_all(mle_code.as_bytes())
+        .expect("Unable to write data");
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "mle_check_only"])
+        .output();
+    let config = Config {
+        exe_path: "mle_check_only".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "mle_check_only.out".to_string(),
+        error_path: "mle_check_only.out".to_string(),
+        log_path: "judger.log".to_string(),
+        memory_limit_mode: MemoryLimitMode::CheckOnly,
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+    let result = run(&config, None).unwrap();
+    println!("{:?}", result);
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.result, ErrorCode::MemoryLimitExceeded);
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("mle_check_only");
+    let _ = std::fs::remove_file("mle_check_only.out");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_ole_ignoring_sigxfsz() {
+    let tmp_file_path = "./ole_ignore.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    // Writes past the limit fail with EFBIG instead of killing the program.
+    let code = r#"
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+int main(int argc, char *argv[]) {
+    signal(SIGXFSZ, SIG_IGN);
+    FILE *out = argc > 1 ? stderr : stdout;
+    char line[1100];
+    memset(line, 'x', sizeof(line));
+    for (int i = 0; i < 1000; i++) {
+        fwrite(line, 1, sizeof(line), out);
+    }
+    return 0;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "ole_ignore"])
+        .output();
+
+    let config = Config {
+        exe_path: "ole_ignore".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "ole_ignore.out".to_string(),
+        error_path: "ole_ignore.err".to_string(),
+        log_path: "ole_ignore.log".to_string(),
+        max_output_size: Some(ByteSize(1000)),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.signal, 0);
+    assert_eq!(result.result, ErrorCode::OutputLimitExceeded);
+
+    // Standard error counts as well.
+    let config = Config {
+        args: vec!["ole_ignore".to_string(), "stderr".to_string()],
+        ..config
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::OutputLimitExceeded);
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("ole_ignore");
+    let _ = std::fs::remove_file("ole_ignore.out");
+    let _ = std::fs::remove_file("ole_ignore.err");
+    let _ = std::fs::remove_file("ole_ignore.log");
+}
@@ -0,0 +1,112 @@
+use judger::{ByteSize, Config, ErrorCode, Namespaces, SeccompRuleName, run};
+use std::io::Write;
+
+#[test]
+fn test_namespaces() {
+    let tmp_file_path = "./namespace.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let namespace_code = r#"
+#include <arpa/inet.h>
+#include <signal.h>
+#include <sys/socket.h>
+#include <unistd.h>
+int main() {
+    // The init of the namespace is PID 1.
+    if (getpid() != 2) {
+        return 1;
+    }
+    if (kill(-1, 0) == 0) {
+        return 2;
+    }
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    struct sockaddr_in addr = {0};
+    addr.sin_family = AF_INET;
+    addr.sin_port = htons(53);
+    inet_pton(AF_INET, "1.1.1.1", &addr.sin_addr);
+    if (fd >= 0 && connect(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0) {
+        return 3;
+    }
+    return 0;
+}"#;
+    file.write_all(namespace_code.as_bytes())
+        .expect("Unable to write data");
+
+    let input_file_path = "namespace.in";
+    let mut input_file =
+        std::fs::File::create(input_file_path).expect("Unable to create input file");
+    input_file
+        .write_all("\n".as_bytes())
+        .expect("Unable to write input data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "namespace"])
+        .output();
+
+    let config = Config {
+        exe_path: "namespace".to_string(),
+        input_path: input_file_path.to_string(),
+        output_path: "namespace.out".to_string(),
+        error_path: "namespace.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: None,
+        namespaces: Namespaces::all(),
+        ..Default::default()
+    };
+
+    let result = run(&config, None);
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    println!("{:?}", result);
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.result, ErrorCode::Success);
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file(input_file_path);
+    let _ = std::fs::remove_file("namespace");
+    let _ = std::fs::remove_file("namespace.out");
+    let _ = std::fs::remove_file("namespace.err");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_ole_in_pid_namespace() {
+    let tmp_file_path = "./namespace_ole.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let code = r#"
+#include <stdio.h>
+int main() {
+    for (int i = 0; i < 100000; i++) {
+        printf("Hello, World!\n");
+    }
+    return 0;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "namespace_ole"])
+        .output();
+
+    // SIGXFSZ must reach the program, which is not the init of its namespace.
+    let config = Config {
+        exe_path: "namespace_ole".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "namespace_ole.out".to_string(),
+        error_path: "namespace_ole.out".to_string(),
+        log_path: "namespace_ole.log".to_string(),
+        max_output_size: Some(ByteSize(1000)),
+        seccomp_rule_name: Some(SeccompRuleName::CCpp),
+        namespaces: Namespaces {
+            pid: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.signal, nix::libc::SIGXFSZ);
+    assert_eq!(result.result, ErrorCode::OutputLimitExceeded);
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("namespace_ole");
+    let _ = std::fs::remove_file("namespace_ole.out");
+    let _ = std::fs::remove_file("namespace_ole.log");
+}
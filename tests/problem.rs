@@ -0,0 +1,58 @@
+use judger::{ErrorCode, LanguageRegistry, Problem, judge};
+use std::io::Write;
+
+fn write_file(path: &str, content: &str) {
+    let mut file = std::fs::File::create(path).expect("Unable to create file");
+    file.write_all(content.as_bytes())
+        .expect("Unable to write data");
+}
+
+#[test]
+fn test_judge_problem() {
+    std::fs::create_dir_all("problem_pkg/tests").expect("Unable to create package");
+    write_file(
+        "problem_pkg/problem.toml",
+        r#"time_limit = 1000
+memory_limit = 134217728
+tests = "tests/*.in"
+check_mode = "token"
+
+[languages.python]
+time_limit = 3000
+"#,
+    );
+    write_file("problem_pkg/tests/1.in", "1 2\n");
+    write_file("problem_pkg/tests/1.ans", "3\n");
+    write_file("problem_pkg/tests/2.in", "5 5\n");
+    write_file("problem_pkg/tests/2.ans", "10\n");
+    write_file(
+        "problem.c",
+        r#"#include <stdio.h>
+int main() {
+    int a, b;
+    scanf("%d %d", &a, &b);
+    printf("%d\n", a == 5 ? 0 : a + b);
+    return 0;
+}"#,
+    );
+
+    let problem = Problem::from_dir("problem_pkg").unwrap();
+    assert_eq!(problem.languages["python"].time_limit, Some(3000));
+
+    let languages = LanguageRegistry::default();
+    let report = judge("problem_pkg", &languages, "c", "problem.c").unwrap();
+    assert_eq!(report.compile.result, ErrorCode::Success);
+    assert_eq!(report.tests.len(), 2);
+    assert_eq!(
+        report.tests[0].as_ref().map(|result| result.result.clone()),
+        Some(ErrorCode::Success)
+    );
+    assert_eq!(report.score.map(|score| score.score), Some(50.0));
+
+    let report = judge("problem_pkg", &languages, "brainfuck", "problem.c");
+    assert!(report.is_err());
+
+    // clean up
+    let _ = std::fs::remove_dir_all("problem_pkg");
+    let _ = std::fs::remove_file("problem.c");
+}
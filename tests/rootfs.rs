@@ -0,0 +1,108 @@
+use judger::{BindMount, ByteSize, Config, ErrorCode, RootFs, run};
+use std::io::Write;
+
+#[test]
+fn test_rootfs() {
+    let tmp_file_path = "./rootfs.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let rootfs_code = r#"
+#include <stdio.h>
+#include <unistd.h>
+int main() {
+    if (access("/etc/hostname", F_OK) == 0) {
+        return 1;
+    }
+    FILE *tmp = fopen("/tmp/scratch.txt", "w");
+    if (tmp == NULL) {
+        return 2;
+    }
+    fclose(tmp);
+    if (fopen("/sandbox/unauthorized_write.txt", "w") != NULL) {
+        return 3;
+    }
+    return 0;
+}"#;
+    file.write_all(rootfs_code.as_bytes())
+        .expect("Unable to write data");
+
+    let input_file_path = "rootfs.in";
+    let mut input_file =
+        std::fs::File::create(input_file_path).expect("Unable to create input file");
+    input_file
+        .write_all("\n".as_bytes())
+        .expect("Unable to write input data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "rootfs"])
+        .output();
+
+    let current_dir = std::env::current_dir().expect("Unable to get current directory");
+    let mut rootfs = RootFs::default();
+    rootfs.bind_mounts.push(BindMount::read_only(
+        current_dir.to_str().expect("Invalid current directory"),
+        "/sandbox",
+    ));
+    rootfs.work_dir = "/sandbox".to_string();
+
+    let config = Config {
+        exe_path: "/sandbox/rootfs".to_string(),
+        input_path: input_file_path.to_string(),
+        output_path: "rootfs.out".to_string(),
+        error_path: "rootfs.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: None,
+        rootfs: Some(rootfs),
+        ..Default::default()
+    };
+
+    let result = run(&config, None);
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    println!("{:?}", result);
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.result, ErrorCode::Success);
+    assert!(!std::path::Path::new("unauthorized_write.txt").exists());
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file(input_file_path);
+    let _ = std::fs::remove_file("rootfs");
+    let _ = std::fs::remove_file("rootfs.out");
+    let _ = std::fs::remove_file("rootfs.err");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_rootfs_size() {
+    let rootfs = RootFs {
+        root_size: ByteSize::kib(256),
+        ..Default::default()
+    };
+    let config = Config {
+        exe_path: "/bin/true".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: "/dev/null".to_string(),
+        log_path: "rootfs_size.log".to_string(),
+        seccomp_rule_name: None,
+        rootfs: Some(rootfs),
+        ..Default::default()
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+
+    // A tmpfs of size 0 would be unlimited.
+    let mut config = config;
+    if let Some(rootfs) = &mut config.rootfs {
+        rootfs.root_size = ByteSize(0);
+    }
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors[0].field, "rootfs.root_size");
+
+    if let Some(rootfs) = &mut config.rootfs {
+        rootfs.root_size = ByteSize::kib(256);
+        rootfs.tmp_size = Some(ByteSize(0));
+    }
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors[0].field, "rootfs.tmp_size");
+    let _ = std::fs::remove_file("rootfs_size.log");
+}
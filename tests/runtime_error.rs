@@ -1,5 +1,7 @@
-use judger::{Config, SeccompRuleName, run};
+use judger::{Config, ErrorCode, JudgerError, SeccompRuleName, run};
 use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
 
 #[test]
 fn test_syscall_error() {
@@ -39,7 +41,8 @@ int main() {
     let result = run(&config, None);
     assert!(result.is_ok());
     let result = result.unwrap();
-    assert_eq!(result.result, judger::ErrorCode::RuntimeError);
+    assert_eq!(result.result, judger::ErrorCode::SyscallViolation);
+    assert_eq!(result.syscall.as_deref(), Some("openat"));
     // clean up
     let _ = std::fs::remove_file(tmp_file_path);
     let _ = std::fs::remove_file(input_file_path);
@@ -48,3 +51,92 @@ int main() {
     let _ = std::fs::remove_file("1.err");
     let _ = std::fs::remove_file("judger.log");
 }
+
+#[test]
+fn test_syscall_error_in_child() {
+    let tmp_file_path = "./syscall_child.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    // A caught SIGSYS must not hide the violation, not even in a forked child.
+    let code = r#"#include <signal.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+static void ignore(int signal) { (void)signal; }
+int main() {
+    signal(SIGSYS, ignore);
+    pid_t child = fork();
+    if (child == 0) {
+        socket(AF_INET, SOCK_STREAM, 0);
+        _exit(0);
+    }
+    waitpid(child, NULL, 0);
+    return 0;
+}"#;
+    file.write_all(code.as_bytes())
+        .expect("Unable to write data");
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "syscall_child"])
+        .output();
+
+    let config = Config {
+        exe_path: "syscall_child".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "syscall_child.out".to_string(),
+        error_path: "syscall_child.out".to_string(),
+        log_path: "syscall_child.log".to_string(),
+        max_process_number: None,
+        seccomp_rule_name: Some(SeccompRuleName::Golang),
+        ..Default::default()
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::SyscallViolation);
+    assert_eq!(result.syscall.as_deref(), Some("socket"));
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file("syscall_child");
+    let _ = std::fs::remove_file("syscall_child.out");
+    let _ = std::fs::remove_file("syscall_child.log");
+}
+
+#[test]
+fn test_sandbox_error() {
+    // Exists and is executable, but is no program the kernel can run.
+    std::fs::write("not_a_program", "not a program").expect("Unable to create file");
+    std::fs::set_permissions("not_a_program", std::fs::Permissions::from_mode(0o755))
+        .expect("Unable to make file executable");
+    let config = Config {
+        exe_path: "./not_a_program".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: "/dev/null".to_string(),
+        log_path: "sandbox_error.log".to_string(),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+
+    // A program that cannot be started is a failure of the sandbox, not a verdict.
+    let error = run(&config, None).unwrap_err();
+    assert_eq!(error.code(), ErrorCode::ExecveFailed);
+    let source = std::error::Error::source(&error)
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .unwrap();
+    assert_eq!(source.raw_os_error(), Some(nix::libc::ENOEXEC));
+
+    let error = run(
+        &Config {
+            exe_path: "./does_not_exist".to_string(),
+            max_cpu_time: Some(Duration::ZERO),
+            ..config
+        },
+        None,
+    )
+    .unwrap_err();
+    let JudgerError::InvalidConfig(message) = error else {
+        panic!("expected an invalid config, got {:?}", error);
+    };
+    assert!(message.contains("max_cpu_time: is zero"));
+    assert!(message.contains("exe_path: ./does_not_exist"));
+    // clean up
+    let _ = std::fs::remove_file("not_a_program");
+    let _ = std::fs::remove_file("sandbox_error.log");
+}
@@ -0,0 +1,80 @@
+use judger::{Aggregation, ErrorCode, RunResult, Scoring, Subtask, SubtaskStatus};
+
+fn verdict(result: ErrorCode, score: Option<f64>) -> RunResult {
+    RunResult {
+        result,
+        score,
+        ..Default::default()
+    }
+}
+
+fn subtask(name: &str, points: f64, aggregation: Aggregation, tests: Vec<usize>) -> Subtask {
+    Subtask {
+        name: name.to_string(),
+        points,
+        aggregation,
+        tests,
+        weights: vec![],
+        dependencies: vec![],
+    }
+}
+
+#[test]
+fn test_scoring() {
+    let results = vec![
+        verdict(ErrorCode::Success, None),
+        verdict(ErrorCode::PartiallyCorrect("half".to_string()), Some(0.5)),
+        verdict(ErrorCode::WrongAnswer("no".to_string()), Some(0.0)),
+        verdict(ErrorCode::Success, Some(1.0)),
+    ];
+    let scoring = Scoring {
+        subtasks: vec![
+            subtask("first", 20.0, Aggregation::Min, vec![0, 1]),
+            Subtask {
+                weights: vec![1.0, 1.0, 2.0],
+                ..subtask("second", 40.0, Aggregation::Sum, vec![1, 2, 3])
+            },
+            Subtask {
+                dependencies: vec!["first".to_string()],
+                ..subtask("third", 40.0, Aggregation::Min, vec![3])
+            },
+        ],
+        skip_on_failure: false,
+    };
+
+    let report = scoring.score(&results).unwrap();
+    assert_eq!(report.max_score, 100.0);
+    assert_eq!(report.subtasks[0].score, 10.0);
+    assert_eq!(report.subtasks[0].status, SubtaskStatus::Partial);
+    assert_eq!(report.subtasks[1].score, 25.0);
+    assert_eq!(report.subtasks[2].status, SubtaskStatus::Skipped);
+    assert_eq!(report.score, 35.0);
+    assert!(serde_json::to_string(&report).is_ok());
+}
+
+#[test]
+fn test_scoring_skips_after_failure() {
+    let scoring = Scoring {
+        subtasks: vec![subtask("only", 100.0, Aggregation::Min, vec![0, 1, 2])],
+        skip_on_failure: true,
+    };
+    let mut runs = vec![];
+    let report = scoring
+        .run(|index| {
+            runs.push(index);
+            Ok(verdict(ErrorCode::RuntimeError, None))
+        })
+        .unwrap();
+    assert_eq!(runs, vec![0]);
+    assert_eq!(report.score, 0.0);
+    assert!(report.subtasks[0].tests[1].result.is_none());
+
+    let scoring = Scoring {
+        subtasks: vec![Subtask {
+            dependencies: vec!["later".to_string()],
+            ..subtask("only", 100.0, Aggregation::Min, vec![0])
+        }],
+        skip_on_failure: false,
+    };
+    assert!(scoring.validate().is_err());
+}
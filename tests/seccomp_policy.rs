@@ -0,0 +1,65 @@
+use judger::{Config, ErrorCode, PolicyAction, SeccompPolicy, run};
+use std::io::Write;
+
+#[test]
+fn test_seccomp_policy() {
+    let tmp_file_path = "./policy.c";
+    let mut file = std::fs::File::create(tmp_file_path).expect("Unable to create file");
+    let policy_code = r#"#include <stdio.h>
+int main() {
+    FILE *fp = fopen("policy_write.txt", "w");
+    fprintf(fp, "This write operation should be blocked by seccomp!\n");
+    return 0;
+}"#;
+    file.write_all(policy_code.as_bytes())
+        .expect("Unable to write data");
+
+    let input_file_path = "policy.in";
+    let mut input_file =
+        std::fs::File::create(input_file_path).expect("Unable to create input file");
+    input_file
+        .write_all("\n".as_bytes())
+        .expect("Unable to write input data");
+
+    let _ = std::process::Command::new("gcc")
+        .args([tmp_file_path, "-o", "policy"])
+        .output();
+
+    let policy =
+        SeccompPolicy::from_file("assets/seccomp/c_cpp.toml").expect("Unable to load policy");
+    assert_eq!(policy.default_action, PolicyAction::Trap);
+    assert!(policy.validate().is_ok());
+
+    let config = Config {
+        exe_path: "policy".to_string(),
+        input_path: input_file_path.to_string(),
+        output_path: "policy.out".to_string(),
+        error_path: "policy.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: None,
+        seccomp_policy: Some(policy),
+        ..Default::default()
+    };
+
+    let result = run(&config, None);
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    println!("{:?}", result);
+    assert_eq!(result.result, ErrorCode::SyscallViolation);
+    assert_eq!(result.syscall.as_deref(), Some("openat"));
+    // clean up
+    let _ = std::fs::remove_file(tmp_file_path);
+    let _ = std::fs::remove_file(input_file_path);
+    let _ = std::fs::remove_file("policy");
+    let _ = std::fs::remove_file("policy.out");
+    let _ = std::fs::remove_file("policy.err");
+    let _ = std::fs::remove_file("judger.log");
+}
+
+#[test]
+fn test_seccomp_policy_unknown_syscall() {
+    let policy: SeccompPolicy =
+        serde_json::from_str(r#"{"default_action": "allow", "deny": ["not_a_syscall"]}"#)
+            .expect("Unable to parse policy");
+    assert!(policy.validate().is_err());
+}
@@ -0,0 +1,126 @@
+use judger::{Config, ErrorCode, SeccompRuleName, run};
+use std::io::Write;
+
+fn write_file(path: &str, content: &str) {
+    let mut file = std::fs::File::create(path).expect("Unable to create file");
+    file.write_all(content.as_bytes())
+        .expect("Unable to write data");
+}
+
+#[test]
+fn test_special_judge() {
+    write_file(
+        "./spj_user.c",
+        r#"#include <stdio.h>
+int main() {
+    int a, b;
+    scanf("%d %d", &a, &b);
+    printf("%d\n", a + b);
+    return 0;
+}"#,
+    );
+    write_file(
+        "./spj_checker.c",
+        r#"#include <stdio.h>
+#include <stdlib.h>
+int main(int argc, char *argv[]) {
+    int found, expected;
+    FILE *output = fopen(argv[2], "r");
+    FILE *answer = fopen(argv[3], "r");
+    if (argc != 4 || !output || !answer) abort();
+    fscanf(output, "%d", &found);
+    fscanf(answer, "%d", &expected);
+    if (found == expected) return 0;
+    if (found * 2 == expected) {
+        fprintf(stderr, "points 0.5 half of %d", expected);
+        return 7;
+    }
+    fprintf(stderr, "expected %d, found %d", expected, found);
+    return 1;
+}"#,
+    );
+    write_file("spj.in", "1 2\n");
+    write_file("spj_ok.ans", "3\n");
+    write_file("spj_half.ans", "6\n");
+    write_file("spj_wrong.ans", "4\n");
+
+    let _ = std::process::Command::new("gcc")
+        .args(["./spj_user.c", "-o", "spj_user"])
+        .output();
+    let _ = std::process::Command::new("gcc")
+        .args(["./spj_checker.c", "-o", "spj_checker"])
+        .output();
+
+    let checker = Config {
+        exe_path: "spj_checker".to_string(),
+        input_path: "/dev/null".to_string(),
+        output_path: "/dev/null".to_string(),
+        error_path: "spj_checker.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: None,
+        ..Default::default()
+    };
+    let config = Config {
+        exe_path: "spj_user".to_string(),
+        input_path: "spj.in".to_string(),
+        output_path: "spj.out".to_string(),
+        error_path: "spj.err".to_string(),
+        log_path: "judger.log".to_string(),
+        seccomp_rule_name: Some(SeccompRuleName::CCpp),
+        answer_path: Some("spj_ok.ans".to_string()),
+        special_judge: Some(Box::new(checker.clone())),
+        ..Default::default()
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+    assert_eq!(result.score, Some(1.0));
+
+    let config = Config {
+        answer_path: Some("spj_half.ans".to_string()),
+        ..config
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(
+        result.result,
+        ErrorCode::PartiallyCorrect("0.5 half of 6".to_string())
+    );
+    assert_eq!(result.score, Some(0.5));
+
+    let config = Config {
+        answer_path: Some("spj_wrong.ans".to_string()),
+        ..config
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(
+        result.result,
+        ErrorCode::WrongAnswer("expected 4, found 3".to_string())
+    );
+    assert_eq!(result.score, Some(0.0));
+
+    // A crashing checker is a judge failure, not a verdict on the output.
+    let config = Config {
+        answer_path: Some("missing.ans".to_string()),
+        ..config
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::SpjError);
+    assert_eq!(result.score, None);
+
+    // clean up
+    for file in [
+        "spj_user.c",
+        "spj_user",
+        "spj_checker.c",
+        "spj_checker",
+        "spj_checker.err",
+        "spj.in",
+        "spj.out",
+        "spj.err",
+        "spj_ok.ans",
+        "spj_half.ans",
+        "spj_wrong.ans",
+        "judger.log",
+    ] {
+        let _ = std::fs::remove_file(file);
+    }
+}
@@ -0,0 +1,118 @@
+use judger::{ByteSize, CheckMode, Config, ErrorCode, InputSource, OutputSink, run};
+use std::os::fd::AsRawFd;
+use std::time::Duration;
+
+fn program_config(name: &str, exe_path: &str, args: &[&str]) -> Config {
+    let mut argv = vec![exe_path.to_string()];
+    argv.extend(args.iter().map(|arg| arg.to_string()));
+    Config {
+        exe_path: exe_path.to_string(),
+        args: argv,
+        log_path: format!("{}.log", name),
+        stdin: Some(InputSource::Null),
+        stdout: Some(OutputSink::Null),
+        stderr: Some(OutputSink::Null),
+        seccomp_rule_name: None,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_in_memory_stdio() {
+    // Larger than a pipe buffer in both directions, so feeding and draining must overlap.
+    let input: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let config = Config {
+        stdin: Some(InputSource::Bytes(input.clone())),
+        stdout: Some(OutputSink::Capture),
+        max_output_size: Some(ByteSize::mib(4)),
+        ..program_config("stdio_memory", "/bin/cat", &[])
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+    assert_eq!(result.stdout.as_deref(), Some(input.as_slice()));
+    assert_eq!(result.stderr, None);
+
+    let config = Config {
+        stdout: Some(OutputSink::Null),
+        stderr: Some(OutputSink::Capture),
+        ..program_config("stdio_memory", "/bin/sh", &["-c", "echo out; echo err >&2"])
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+    assert_eq!(result.stdout, None);
+    assert_eq!(result.stderr.as_deref(), Some(&b"err\n"[..]));
+
+    // Captured output is checked against the answer.
+    std::fs::write("stdio_memory.ans", "1 2 3\n").unwrap();
+    let config = Config {
+        stdin: Some(InputSource::Bytes(b"1 2 3\n".to_vec())),
+        stdout: Some(OutputSink::Capture),
+        answer_path: Some("stdio_memory.ans".to_string()),
+        check_mode: CheckMode::Exact,
+        ..program_config("stdio_memory", "/bin/cat", &[])
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+    assert_eq!(result.score, Some(1.0));
+    let _ = std::fs::remove_file("stdio_memory.ans");
+    let _ = std::fs::remove_file("stdio_memory.log");
+}
+
+#[test]
+fn test_captured_output_limit() {
+    let config = Config {
+        stdin: Some(InputSource::Bytes(vec![b'x'; 100_000])),
+        stdout: Some(OutputSink::Capture),
+        max_output_size: Some(ByteSize(1000)),
+        ..program_config("stdio_limit", "/bin/cat", &[])
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::OutputLimitExceeded);
+    assert_eq!(result.stdout.map(|stdout| stdout.len()), Some(1000));
+
+    // A program that keeps writing is killed once it exceeds the limit.
+    let config = Config {
+        stdout: Some(OutputSink::Capture),
+        max_output_size: Some(ByteSize(1000)),
+        max_real_time: Some(Duration::from_secs(10)),
+        ..program_config("stdio_limit", "/usr/bin/yes", &[])
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::OutputLimitExceeded);
+    assert!(result.real_time < Duration::from_secs(5));
+
+    let config = Config {
+        max_output_size: None,
+        ..config
+    };
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors[0].field, "stdout");
+    let _ = std::fs::remove_file("stdio_limit.log");
+}
+
+#[test]
+fn test_inherited_fd() {
+    std::fs::write("stdio_fd.in", "hello\n").unwrap();
+    let output = std::fs::File::create("stdio_fd.out").unwrap();
+    let config = Config {
+        stdin: Some(InputSource::Path("stdio_fd.in".to_string())),
+        stdout: Some(OutputSink::Fd(output.as_raw_fd())),
+        ..program_config("stdio_fd", "/bin/cat", &[])
+    };
+    let result = run(&config, None).unwrap();
+    assert_eq!(result.result, ErrorCode::Success);
+    assert_eq!(std::fs::read_to_string("stdio_fd.out").unwrap(), "hello\n");
+
+    let config = Config {
+        stdout: Some(OutputSink::Fd(-1)),
+        special_judge: Some(Box::new(program_config("stdio_fd", "/bin/true", &[]))),
+        stdin: Some(InputSource::Bytes(Vec::new())),
+        ..config
+    };
+    let errors = config.validate().unwrap_err();
+    let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(fields, ["stdout", "stdin", "stdout"]);
+    let _ = std::fs::remove_file("stdio_fd.in");
+    let _ = std::fs::remove_file("stdio_fd.out");
+    let _ = std::fs::remove_file("stdio_fd.log");
+}